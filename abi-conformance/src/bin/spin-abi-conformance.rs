@@ -46,7 +46,7 @@ async fn main() -> Result<()> {
         spin_abi_conformance::TestConfig::default()
     };
 
-    let report = &spin_abi_conformance::test(module, engine, config).await?;
+    let report = &spin_abi_conformance::test_world(module, engine, config).await?;
 
     let writer = if let Some(output) = &options.output {
         Box::new(File::create(output)?) as Box<dyn Write>
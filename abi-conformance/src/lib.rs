@@ -18,13 +18,20 @@
 //! [`InvocationStyle`] with a set of arguments indicating which host function to call and with what arguments.
 //! The host then asserts that host function was indeed called with the expected arguments (see
 //! [`Report::http`] for an example).
+//!
+//! Components targeting the `fermyon:spin/platform@2.0.0` world rather than the unversioned `fermyon:spin/host`
+//! world may be tested by setting [`TestConfig::world`] and running the suite via [`test_world()`] instead, which
+//! returns the results of the [`v2`] tests in that case.
 
 #![deny(warnings)]
 
 use anyhow::{anyhow, bail, Context as _, Result};
-use fermyon::spin::http_types::{Method, Request, Response};
+use fermyon::spin::{
+    http_types::{Method, Request, Response},
+    redis_types::Payload,
+};
 use serde::{Deserialize, Serialize};
-use std::{future::Future, str};
+use std::{future::Future, iter, str};
 use test_config::Config;
 use test_http::Http;
 use test_key_value::KeyValue;
@@ -32,12 +39,13 @@ use test_llm::Llm;
 use test_mysql::Mysql;
 use test_postgres::Postgres;
 use test_redis::Redis;
+use test_sqlite::Sqlite;
 use wasmtime::{
     component::{Component, InstancePre, Linker},
     Engine, Store,
 };
 use wasmtime_wasi::preview2::{
-    pipe::MemoryOutputPipe, ResourceTable, WasiCtx, WasiCtxBuilder, WasiView,
+    pipe::MemoryOutputPipe, I32Exit, ResourceTable, WasiCtx, WasiCtxBuilder, WasiView,
 };
 
 pub use test_key_value::KeyValueReport;
@@ -45,6 +53,7 @@ pub use test_llm::LlmReport;
 pub use test_mysql::MysqlReport;
 pub use test_postgres::PostgresReport;
 pub use test_redis::RedisReport;
+pub use test_sqlite::SqliteReport;
 pub use test_wasi::WasiReport;
 
 mod test_config;
@@ -56,8 +65,11 @@ mod test_llm;
 mod test_mysql;
 mod test_postgres;
 mod test_redis;
+mod test_sqlite;
 mod test_wasi;
 
+pub mod v2;
+
 wasmtime::component::bindgen!({
     path: "../wit",
     world: "fermyon:spin/host",
@@ -72,14 +84,42 @@ pub enum InvocationStyle {
     /// via the request body as a string of tokens separated by the delimiter "%20".
     #[default]
     InboundHttp,
+
+    /// The host should call into the guest using the `wasi:http/incoming-handler@0.2.0` export's `handle`
+    /// function, passing arguments via the body of a POST request to "/" as a string of tokens separated by the
+    /// delimiter "%20".  The guest should respond with a 200 OK response on success.
+    WasiHttp,
+
+    /// The host should call into the guest using inbound-redis.wit's `handle-message` function, passing arguments
+    /// via the message payload as a string of tokens separated by the delimiter "%20".  The guest should return
+    /// `ok(unit)` on success.
+    InboundRedis,
+
+    /// The host should call into the guest using the `wasi:cli/run@0.2.0` export's `run` function, passing
+    /// arguments via argv following the program name.  The guest should exit with a zero status on success.
+    Command,
 }
 
-/// Configuration options for the [`test()`] function
+/// The Spin world a component is tested against
+#[derive(Copy, Clone, Default, Deserialize, PartialEq, Eq, Debug)]
+pub enum World {
+    /// The unversioned `fermyon:spin/host` world, tested by [`test()`]
+    #[default]
+    Host,
+    /// The `fermyon:spin/platform@2.0.0` world, tested by the [`v2`] suite
+    Platform2_0_0,
+}
+
+/// Configuration options for the [`test()`] and [`test_world()`] functions
 #[derive(Default, Deserialize, Clone)]
 pub struct TestConfig {
     /// The invocation style to use when the host asks the guest to call a host-implemented function
     #[serde(default)]
     pub invocation_style: InvocationStyle,
+
+    /// The world to test the component against, which is only considered by [`test_world()`]
+    #[serde(default)]
+    pub world: World,
 }
 
 /// Report of which tests succeeded or failed
@@ -97,6 +137,9 @@ pub struct Report {
     /// The guest component should expect a call to `handle-request` with a POST request to "/foo" containing
     /// a single header "foo: bar" and a UTF-8 string body "Hello, SpinHttp!" and return a 200 OK response that
     /// includes a single header "lorem: ipsum" and a UTF-8 string body "dolor sit amet".
+    ///
+    /// With [`InvocationStyle::WasiHttp`], the same request is instead passed to the
+    /// `wasi:http/incoming-handler@0.2.0` export's `handle` function.
     pub inbound_http: Result<(), String>,
 
     /// Result of the Spin inbound Redis test
@@ -137,6 +180,11 @@ pub struct Report {
     /// See [`MysqlReport`] for details.
     pub mysql: MysqlReport,
 
+    /// Results of the Spin SQLite tests
+    ///
+    /// See [`SqliteReport`] for details.
+    pub sqlite: SqliteReport,
+
     /// Results of the Spin key-value tests
     ///
     /// See [`KeyValueReport`] for details.
@@ -164,10 +212,15 @@ pub async fn test(
 ) -> Result<Report> {
     let mut linker = Linker::<Context>::new(engine);
     wasmtime_wasi::preview2::command::add_to_linker(&mut linker)?;
+    // A component invoked via `wasi:http/incoming-handler` imports the `wasi:http` types it is handed
+    if matches!(test_config.invocation_style, InvocationStyle::WasiHttp) {
+        wasmtime_wasi_http::proxy::add_only_http_to_linker(&mut linker)?;
+    }
     http::add_to_linker(&mut linker, |context| &mut context.http)?;
     redis::add_to_linker(&mut linker, |context| &mut context.redis)?;
     postgres::add_to_linker(&mut linker, |context| &mut context.postgres)?;
     mysql::add_to_linker(&mut linker, |context| &mut context.mysql)?;
+    sqlite::add_to_linker(&mut linker, |context| &mut context.sqlite)?;
     key_value::add_to_linker(&mut linker, |context| &mut context.key_value)?;
     llm::add_to_linker(&mut linker, |context| &mut context.llm)?;
     config::add_to_linker(&mut linker, |context| &mut context.config)?;
@@ -175,19 +228,51 @@ pub async fn test(
     let pre = linker.instantiate_pre(component)?;
 
     Ok(Report {
-        inbound_http: test_inbound_http::test(engine, test_config.clone(), &pre).await,
+        inbound_http: match test_config.invocation_style {
+            InvocationStyle::WasiHttp => {
+                v2::test_inbound_http::test(engine, test_config.clone(), &pre).await
+            }
+            _ => test_inbound_http::test(engine, test_config.clone(), &pre).await,
+        },
         inbound_redis: test_inbound_redis::test(engine, test_config.clone(), &pre).await,
         config: test_config::test(engine, test_config.clone(), &pre).await,
         http: test_http::test(engine, test_config.clone(), &pre).await,
         redis: test_redis::test(engine, test_config.clone(), &pre).await?,
         postgres: test_postgres::test(engine, test_config.clone(), &pre).await?,
         mysql: test_mysql::test(engine, test_config.clone(), &pre).await?,
+        sqlite: test_sqlite::test(engine, test_config.clone(), &pre).await?,
         key_value: test_key_value::test(engine, test_config.clone(), &pre).await?,
         llm: test_llm::test(engine, test_config.clone(), &pre).await?,
         wasi: test_wasi::test(engine, test_config, &pre).await?,
     })
 }
 
+/// Report of the suite selected by [`TestConfig::world`]
+#[derive(Serialize, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum WorldReport {
+    /// Results of the `fermyon:spin/host` tests
+    Host(Box<Report>),
+    /// Results of the `fermyon:spin/platform@2.0.0` tests
+    Platform2_0_0(Box<v2::Report>),
+}
+
+/// Run the suite for the world selected by [`TestConfig::world`] against the specified `component`
+///
+/// This is equivalent to [`test()`] for [`World::Host`].
+pub async fn test_world(
+    component: &Component,
+    engine: &Engine,
+    test_config: TestConfig,
+) -> Result<WorldReport> {
+    Ok(match test_config.world {
+        World::Host => WorldReport::Host(Box::new(test(component, engine, test_config).await?)),
+        World::Platform2_0_0 => {
+            WorldReport::Platform2_0_0(Box::new(v2::test(component, engine, test_config).await?))
+        }
+    })
+}
+
 pub(crate) fn create_store(engine: &Engine, test_config: TestConfig) -> Store<Context> {
     create_store_with_context_and_wasi(engine, test_config, |_| {}, |b| b)
 }
@@ -217,8 +302,14 @@ pub(crate) fn create_store_with_context_and_wasi(
     let stderr = MemoryOutputPipe::new(1024);
     let mut builder = WasiCtxBuilder::new();
     builder.stderr(stderr.clone());
-    let wasi = wasi_builder(builder).build();
+    let mut builder = wasi_builder(builder);
+    // A command receives its arguments via argv, so its `WasiCtx` can't be built until `run_command` knows them
+    let (wasi, command_wasi) = match test_config.invocation_style {
+        InvocationStyle::Command => (WasiCtxBuilder::new().build(), Some(builder)),
+        _ => (builder.build(), None),
+    };
     let mut context = Context::new(test_config, wasi, table, stderr);
+    context.command_wasi = command_wasi;
     context_builder(&mut context);
     Store::new(engine, context)
 }
@@ -226,15 +317,19 @@ pub(crate) fn create_store_with_context_and_wasi(
 struct Context {
     test_config: TestConfig,
     wasi: WasiCtx,
+    /// The builder for `wasi` when using [`InvocationStyle::Command`], to which `run_command` adds the arguments
+    command_wasi: Option<WasiCtxBuilder>,
     table: ResourceTable,
     stderr: MemoryOutputPipe,
     http: Http,
     redis: Redis,
     postgres: Postgres,
     mysql: Mysql,
+    sqlite: Sqlite,
     key_value: KeyValue,
     llm: Llm,
     config: Config,
+    v2: v2::Platform,
 }
 
 impl Context {
@@ -247,6 +342,7 @@ impl Context {
         Self {
             test_config,
             wasi,
+            command_wasi: None,
             table,
             stderr,
 
@@ -254,9 +350,11 @@ impl Context {
             redis: Default::default(),
             postgres: Default::default(),
             mysql: Default::default(),
+            sqlite: Default::default(),
             key_value: Default::default(),
             llm: Default::default(),
             config: Default::default(),
+            v2: Default::default(),
         }
     }
 }
@@ -284,7 +382,8 @@ async fn run_command(
     run(async {
         let instance = pre.instantiate_async(&mut *store).await?;
 
-        match store.data().test_config.invocation_style {
+        // The outer `Result` is a trap, while the inner one is a failure reported by the guest
+        let result: Result<Result<(), String>> = match store.data().test_config.invocation_style {
             InvocationStyle::InboundHttp => {
                 let func = instance
                     .exports(&mut *store)
@@ -292,48 +391,114 @@ async fn run_command(
                     .ok_or_else(|| anyhow!("no fermyon:spin/inbound-http instance found"))?
                     .typed_func::<(Request,), (Response,)>("handle-request")?;
 
-                let result = func
-                    .call_async(
-                        &mut *store,
-                        (Request {
-                            method: Method::Post,
-                            uri: "/".into(),
-                            headers: vec![],
-                            params: vec![],
-                            body: Some(arguments.join("%20").into_bytes()),
-                        },),
-                    )
-                    .await;
-
-                // Reset `Context::wasi` and `Context::table` so there are no more
-                // references to the `stderr` pipe, ensuring `try_into_inner` succeeds below.  This is also needed
-                // in case the caller attached its own pipes for e.g. stdin and/or stdout and expects exclusive
-                // ownership once we return.
-                let table = ResourceTable::new();
-                store.data_mut().wasi = WasiCtxBuilder::new().build();
-                *store.data_mut().table() = table;
-                let stderr =
-                    std::mem::replace(&mut store.data_mut().stderr, MemoryOutputPipe::new(1024));
-
-                let (response,) = result.with_context(|| {
-                    String::from_utf8_lossy(&stderr.try_into_inner().unwrap().to_vec()).into_owned()
-                })?;
-
-                if response.status != 200 {
-                    bail!(
-                        "status: {}; body: {}",
+                func.call_async(
+                    &mut *store,
+                    (Request {
+                        method: Method::Post,
+                        uri: "/".into(),
+                        headers: vec![],
+                        params: vec![],
+                        body: Some(arguments.join("%20").into_bytes()),
+                    },),
+                )
+                .await
+                .map(|(response,)| {
+                    http_status(
                         response.status,
-                        response
-                            .body
-                            .as_deref()
-                            .map(|body| String::from_utf8_lossy(body))
-                            .unwrap_or_default()
-                    );
+                        response.body.as_deref().unwrap_or_default(),
+                    )
+                })
+            }
+
+            InvocationStyle::WasiHttp => v2::handle_request(
+                &mut *store,
+                &instance,
+                hyper::Request::post("http://localhost/")
+                    .body(arguments.join("%20").into_bytes().into())?,
+            )
+            .await
+            .map(|response| http_status(response.status().as_u16(), response.body())),
+
+            InvocationStyle::InboundRedis => {
+                let func = instance
+                    .exports(&mut *store)
+                    .instance("fermyon:spin/inbound-redis")
+                    .ok_or_else(|| anyhow!("no fermyon:spin/inbound-redis instance found"))?
+                    .typed_func::<(Payload,), (Result<(), redis_types::Error>,)>(
+                        "handle-message",
+                    )?;
+
+                func.call_async(&mut *store, (arguments.join("%20").into_bytes(),))
+                    .await
+                    .map(|(result,)| match result {
+                        Ok(()) | Err(redis_types::Error::Success) => Ok(()),
+                        Err(e) => Err(format!("error: {e:?}")),
+                    })
+            }
+
+            InvocationStyle::Command => {
+                let mut builder = store
+                    .data_mut()
+                    .command_wasi
+                    .take()
+                    .context("store was not created for `InvocationStyle::Command`")?;
+                builder.args(
+                    &iter::once("<wasm module>")
+                        .chain(arguments.iter().copied())
+                        .collect::<Vec<_>>(),
+                );
+                store.data_mut().wasi = builder.build();
+
+                let func = instance
+                    .exports(&mut *store)
+                    .instance("wasi:cli/run@0.2.0")
+                    .ok_or_else(|| anyhow!("no wasi:cli/run@0.2.0 instance found"))?
+                    .typed_func::<(), (Result<(), ()>,)>("run")?;
+
+                match func.call_async(&mut *store, ()).await {
+                    Ok((Ok(()),)) => Ok(Ok(())),
+                    Ok((Err(()),)) => Ok(Err("command exited with a failure status".into())),
+                    Err(e) => match e.downcast_ref::<I32Exit>() {
+                        Some(I32Exit(0)) => Ok(Ok(())),
+                        Some(I32Exit(status)) => {
+                            Ok(Err(format!("command exited with status {status}")))
+                        }
+                        None => Err(e),
+                    },
                 }
             }
+        };
+
+        // Reset `Context::wasi` and `Context::table` so there are no more
+        // references to the `stderr` pipe, ensuring `try_into_inner` succeeds below.  This is also needed
+        // in case the caller attached its own pipes for e.g. stdin and/or stdout and expects exclusive
+        // ownership once we return.
+        let table = ResourceTable::new();
+        store.data_mut().wasi = WasiCtxBuilder::new().build();
+        *store.data_mut().table() = table;
+        let stderr = std::mem::replace(&mut store.data_mut().stderr, MemoryOutputPipe::new(1024));
+        let stderr =
+            || String::from_utf8_lossy(&stderr.try_into_inner().unwrap().to_vec()).into_owned();
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(failure)) => bail!("{failure}; stderr: {}", stderr()),
+            Err(e) => return Err(e.context(stderr())),
         }
 
         fun(store)
     })
     .await
 }
+
+/// Treat any status but 200 OK as a failure reported by the guest
+fn http_status(status: u16, body: &[u8]) -> Result<(), String> {
+    if status == 200 {
+        Ok(())
+    } else {
+        Err(format!(
+            "status: {status}; body: {}",
+            String::from_utf8_lossy(body)
+        ))
+    }
+}
@@ -17,7 +17,7 @@ pub(crate) async fn test(
         let func = instance
             .exports(&mut store)
             .instance("fermyon:spin/inbound-redis")
-            .ok_or_else(|| anyhow!("no inbound-redis instance found"))?
+            .ok_or_else(|| anyhow!("no fermyon:spin/inbound-redis instance found"))?
             .typed_func::<(Payload,), (Result<(), Error>,)>("handle-message")?;
 
         match func
@@ -6,15 +6,40 @@ use serde::Serialize;
 
 use crate::llm;
 
-/// Report of which key-value functions a module successfully used, if any
+/// Report of which llm functions a module successfully used, if any
 #[derive(Serialize, PartialEq, Eq, Debug)]
 pub struct LlmReport {
+    /// Result of the llm inferencing test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["llm-infer", "model",
+    /// "Say hello"\] as arguments.  The module should call the host-implemented `llm::infer` function with the
+    /// arguments \["model", "Say hello", `none`\].  The host will assert that said function is called exactly once
+    /// with the specified arguments.
     pub infer: Result<(), String>,
+
+    /// Result of the llm inferencing test with parameters
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["llm-infer", "model",
+    /// "Say hello", "max-tokens:42", "repeat-penalty:1.5", "repeat-penalty-last-n-token-count:64",
+    /// "temperature:0.5", "top-k:10", "top-p:0.75"\] as arguments.  The module should call the host-implemented
+    /// `llm::infer` function with the arguments \["model", "Say hello", `some({ max-tokens: 42, repeat-penalty:
+    /// 1.5, repeat-penalty-last-n-token-count: 64, temperature: 0.5, top-k: 10, top-p: 0.75 })`\].  The host will
+    /// assert that said function is called exactly once with the specified arguments.
+    pub infer_with_params: Result<(), String>,
+
+    /// Result of the llm embeddings test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["llm-generate-embeddings", "model", "foo", "bar"\] as arguments.  The module should call the
+    /// host-implemented `llm::generate-embeddings` function with the arguments \["model", \["foo", "bar"\]\] and
+    /// expect `ok({ embeddings: \[\[1.0, 2.0\], \[3.0, 4.0\]\], usage: { prompt-token-count: 0 } })` as the result.
+    /// The host will assert that said function is called exactly once with the specified arguments.
+    pub embeddings: Result<(), String>,
 }
 
 #[derive(Default)]
 pub struct Llm {
-    inferences: HashMap<(String, String), String>,
+    inferences: HashMap<(String, String, String), String>,
     embeddings: HashMap<(String, Vec<String>), Vec<Vec<f32>>>,
 }
 
@@ -24,11 +49,12 @@ impl llm::Host for Llm {
         &mut self,
         model: llm::InferencingModel,
         prompt: String,
-        _params: Option<llm::InferencingParams>,
+        params: Option<llm::InferencingParams>,
     ) -> wasmtime::Result<Result<llm::InferencingResult, llm::Error>> {
+        let key = (model, prompt, format!("{params:?}"));
         Ok(self
             .inferences
-            .remove(&(model, prompt.clone()))
+            .remove(&key)
             .map(|r| llm::InferencingResult {
                 text: r,
                 usage: llm::InferencingUsage {
@@ -40,7 +66,7 @@ impl llm::Host for Llm {
                 llm::Error::RuntimeError(format!(
                     "expected {:?}, got {:?}",
                     self.inferences.keys(),
-                    prompt
+                    key
                 ))
             }))
     }
@@ -75,23 +101,47 @@ pub(crate) async fn test(
     pre: &wasmtime::component::InstancePre<crate::Context>,
 ) -> Result<LlmReport> {
     Ok(LlmReport {
-        infer: {
-            let mut store =
-                crate::create_store_with_context(engine, test_config.clone(), |context| {
-                    context
-                        .llm
-                        .inferences
-                        .insert(("model".into(), "Say hello".into()), "hello".into());
-                });
+        infer: test_infer(engine, test_config.clone(), pre, &[], None).await,
+
+        infer_with_params: test_infer(
+            engine,
+            test_config.clone(),
+            pre,
+            &[
+                "max-tokens:42",
+                "repeat-penalty:1.5",
+                "repeat-penalty-last-n-token-count:64",
+                "temperature:0.5",
+                "top-k:10",
+                "top-p:0.75",
+            ],
+            Some(llm::InferencingParams {
+                max_tokens: 42,
+                repeat_penalty: 1.5,
+                repeat_penalty_last_n_token_count: 64,
+                temperature: 0.5,
+                top_k: 10,
+                top_p: 0.75,
+            }),
+        )
+        .await,
+
+        embeddings: {
+            let mut store = crate::create_store_with_context(engine, test_config, |context| {
+                context.llm.embeddings.insert(
+                    ("model".into(), vec!["foo".into(), "bar".into()]),
+                    vec![vec![1.0, 2.0], vec![3.0, 4.0]],
+                );
+            });
 
             crate::run_command(
                 &mut store,
                 pre,
-                &["llm-infer", "model", "Say hello"],
+                &["llm-generate-embeddings", "model", "foo", "bar"],
                 |store| {
                     ensure!(
-                        store.data().llm.inferences.is_empty(),
-                        "expected module to call `llm::infer` exactly once"
+                        store.data().llm.embeddings.is_empty(),
+                        "expected module to call `llm::generate_embeddings` exactly once"
                     );
 
                     Ok(())
@@ -101,3 +151,34 @@ pub(crate) async fn test(
         },
     })
 }
+
+async fn test_infer(
+    engine: &wasmtime::Engine,
+    test_config: crate::TestConfig,
+    pre: &wasmtime::component::InstancePre<crate::Context>,
+    arguments: &[&str],
+    params: Option<llm::InferencingParams>,
+) -> Result<(), String> {
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.llm.inferences.insert(
+            ("model".into(), "Say hello".into(), format!("{params:?}")),
+            "hello".into(),
+        );
+    });
+
+    let arguments = ["llm-infer", "model", "Say hello"]
+        .iter()
+        .chain(arguments)
+        .copied()
+        .collect::<Vec<_>>();
+
+    crate::run_command(&mut store, pre, &arguments, |store| {
+        ensure!(
+            store.data().llm.inferences.is_empty(),
+            "expected module to call `llm::infer` exactly once"
+        );
+
+        Ok(())
+    })
+    .await
+}
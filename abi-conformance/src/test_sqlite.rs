@@ -0,0 +1,234 @@
+use crate::{
+    sqlite::{self, Connection, Error, QueryResult, RowResult, Value},
+    Context, TestConfig,
+};
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+use wasmtime::{component::InstancePre, Engine};
+
+/// Report of which SQLite functions a module successfully used, if any
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct SqliteReport {
+    /// Result of the SQLite open test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["sqlite-open",
+    /// "default"\] as arguments.  The module should call the host-implemented `sqlite::open` function with
+    /// "default" as the argument and expect `ok(42)` as the result.  The host will assert that said function is
+    /// called exactly once with the expected argument.
+    pub open: Result<(), String>,
+
+    /// Result of the SQLite open test for an unknown database
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["sqlite-open", "foo",
+    /// "no-such-database"\] as arguments.  The module should call the host-implemented `sqlite::open` function
+    /// with "foo" as the argument and expect `err(no-such-database)` as the result.  The host will assert that said
+    /// function is called exactly once with the expected argument.
+    pub open_no_such_database: Result<(), String>,
+
+    /// Result of the SQLite open test for a forbidden database
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["sqlite-open", "bar",
+    /// "access-denied"\] as arguments.  The module should call the host-implemented `sqlite::open` function with
+    /// "bar" as the argument and expect `err(access-denied)` as the result.  The host will assert that said function
+    /// is called exactly once with the expected argument.
+    pub open_access_denied: Result<(), String>,
+
+    /// Result of the SQLite statement execution test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["sqlite-execute",
+    /// "42", "INSERT INTO foo (a, b, c, d, e) VALUES (?, ?, ?, ?, ?) RETURNING *", "integer:42", "real:3.5",
+    /// "text:baz", "blob:qux", "null"\] as arguments.  The module should call the host-implemented
+    /// `sqlite::execute` function with the arguments \[42, "INSERT INTO foo (a, b, c, d, e) VALUES (?, ?, ?, ?, ?)
+    /// RETURNING *", `\[integer(42), real(3.5), text("baz"), blob("qux"), null\]`\] and expect `ok({ columns: \["a",
+    /// "b", "c", "d", "e"\], rows: \[ { values: \[integer(42), real(3.5), text("baz"), blob("qux"), null\] } \]
+    /// })` as the result.  The host will assert that said function is called exactly once with the specified
+    /// arguments.
+    pub execute: Result<(), String>,
+
+    /// Result of the SQLite close test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["sqlite-close",
+    /// "42"\] as arguments.  The module should call the host-implemented `sqlite::close` function with 42 as the
+    /// argument.  The host will assert that said function is called exactly once with the expected argument.
+    pub close: Result<(), String>,
+}
+
+#[derive(Default)]
+pub(crate) struct Sqlite {
+    open_map: HashMap<String, Result<Connection, Error>>,
+    execute_map: HashMap<(Connection, String, String), QueryResult>,
+    close_set: HashSet<Connection>,
+}
+
+#[async_trait]
+impl sqlite::Host for Sqlite {
+    async fn open(&mut self, database: String) -> Result<Result<Connection, Error>> {
+        Ok(self.open_map.remove(&database).unwrap_or_else(|| {
+            Err(Error::Io(format!(
+                "expected {:?}, got {:?}",
+                self.open_map.keys(),
+                database
+            )))
+        }))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Connection,
+        statement: String,
+        parameters: Vec<Value>,
+    ) -> Result<Result<QueryResult, Error>> {
+        Ok(self
+            .execute_map
+            .remove(&(connection, statement.to_owned(), format!("{parameters:?}")))
+            .ok_or_else(|| {
+                Error::Io(format!(
+                    "expected {:?}, got {:?}",
+                    self.execute_map.keys(),
+                    iter::once(&(connection, statement, format!("{parameters:?}")))
+                ))
+            }))
+    }
+
+    async fn close(&mut self, connection: Connection) -> Result<()> {
+        if self.close_set.remove(&connection) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "expected {:?}, got {:?}",
+                self.close_set.iter(),
+                iter::once(&connection)
+            ))
+        }
+    }
+}
+
+pub(crate) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<SqliteReport> {
+    Ok(SqliteReport {
+        open: test_open(engine, test_config.clone(), pre, "default", Ok(42)).await,
+        open_no_such_database: test_open(
+            engine,
+            test_config.clone(),
+            pre,
+            "foo",
+            Err(Error::NoSuchDatabase),
+        )
+        .await,
+        open_access_denied: test_open(
+            engine,
+            test_config.clone(),
+            pre,
+            "bar",
+            Err(Error::AccessDenied),
+        )
+        .await,
+        execute: test_execute(engine, test_config.clone(), pre).await,
+        close: {
+            let mut store = crate::create_store_with_context(engine, test_config, |context| {
+                context.sqlite.close_set.insert(42);
+            });
+
+            crate::run_command(&mut store, pre, &["sqlite-close", "42"], |store| {
+                ensure!(
+                    store.data().sqlite.close_set.is_empty(),
+                    "expected module to call `sqlite::close` exactly once"
+                );
+
+                Ok(())
+            })
+            .await
+        },
+    })
+}
+
+async fn test_open(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+    database: &str,
+    result: Result<Connection, Error>,
+) -> Result<(), String> {
+    let error = match &result {
+        Ok(_) => None,
+        Err(Error::NoSuchDatabase) => Some("no-such-database"),
+        Err(Error::AccessDenied) => Some("access-denied"),
+        Err(e) => unreachable!("no test expects {e:?}"),
+    };
+
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.sqlite.open_map.insert(database.into(), result);
+    });
+
+    let arguments = ["sqlite-open", database]
+        .into_iter()
+        .chain(error)
+        .collect::<Vec<_>>();
+
+    crate::run_command(&mut store, pre, &arguments, |store| {
+        ensure!(
+            store.data().sqlite.open_map.is_empty(),
+            "expected module to call `sqlite::open` exactly once"
+        );
+
+        Ok(())
+    })
+    .await
+}
+
+async fn test_execute(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    let statement = "INSERT INTO foo (a, b, c, d, e) VALUES (?, ?, ?, ?, ?) RETURNING *";
+    let values = vec![
+        Value::Integer(42),
+        Value::Real(3.5),
+        Value::Text("baz".into()),
+        Value::Blob(b"qux".to_vec()),
+        Value::Null,
+    ];
+
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.sqlite.execute_map.insert(
+            (42, statement.into(), format!("{values:?}")),
+            QueryResult {
+                columns: ["a", "b", "c", "d", "e"].map(String::from).to_vec(),
+                rows: vec![RowResult { values }],
+            },
+        );
+    });
+
+    crate::run_command(
+        &mut store,
+        pre,
+        &[
+            "sqlite-execute",
+            "42",
+            statement,
+            "integer:42",
+            "real:3.5",
+            "text:baz",
+            "blob:qux",
+            "null",
+        ],
+        |store| {
+            ensure!(
+                store.data().sqlite.execute_map.is_empty(),
+                "expected module to call `sqlite::execute` exactly once"
+            );
+
+            Ok(())
+        },
+    )
+    .await
+}
@@ -0,0 +1,287 @@
+//! Tests for components targeting the `fermyon:spin/platform@2.0.0` world
+//!
+//! Spin 2.0 replaced the handle-based APIs of the unversioned `fermyon:spin/host` world with resources (e.g. a
+//! `key-value.store` opened by label, or a `redis.connection` opened by address) and its outbound HTTP API with
+//! `wasi:http/outgoing-handler`.  This suite is selected by setting [`crate::TestConfig::world`] to
+//! [`crate::World::Platform2_0_0`] and produces a [`Report`] rather than a [`crate::Report`].
+//!
+//! Each host-implemented function is tested by calling into the guest according to [`crate::InvocationStyle`],
+//! just like the `fermyon:spin/host` tests.  Since resources can't be passed in as arguments, the guest is
+//! expected to open the resource named by the arguments (e.g. the store label or connection address) and then
+//! call the method under test on it.
+
+use crate::{Context, TestConfig};
+use anyhow::{anyhow, Context as _, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use serde::Serialize;
+use std::sync::Arc;
+use test_http::Http;
+use test_key_value::KeyValue;
+use test_mysql::Mysql;
+use test_postgres::Postgres;
+use test_redis::Redis;
+use test_sqlite::Sqlite;
+use test_variables::Variables;
+use tokio::sync::oneshot;
+use wasmtime::{
+    component::{Component, Instance, Linker, Resource},
+    Engine, Store,
+};
+use wasmtime_wasi::preview2::{self, ResourceTable};
+use wasmtime_wasi_http::{
+    types::{
+        HostFutureIncomingResponse, HostIncomingRequest, HostResponseOutparam,
+        IncomingResponseInternal, OutgoingRequest,
+    },
+    WasiHttpCtx, WasiHttpView,
+};
+
+pub use test_key_value::KeyValueReport;
+pub use test_mysql::MysqlReport;
+pub use test_postgres::PostgresReport;
+pub use test_redis::RedisReport;
+pub use test_sqlite::SqliteReport;
+
+mod test_http;
+pub(crate) mod test_inbound_http;
+mod test_key_value;
+mod test_mysql;
+mod test_postgres;
+mod test_redis;
+mod test_sqlite;
+mod test_variables;
+
+wasmtime::component::bindgen!({
+    path: "../wit",
+    interfaces: "
+        import fermyon:spin/variables@2.0.0;
+        import fermyon:spin/key-value@2.0.0;
+        import fermyon:spin/redis@2.0.0;
+        import fermyon:spin/postgres@2.0.0;
+        import fermyon:spin/mysql@2.0.0;
+        import fermyon:spin/sqlite@2.0.0;
+        import fermyon:spin/llm@2.0.0;
+    ",
+    async: true
+});
+pub use fermyon::spin2_0_0::*;
+
+/// Report of which `fermyon:spin/platform@2.0.0` tests succeeded or failed
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Report {
+    /// Result of the `wasi:http` inbound HTTP test
+    ///
+    /// The guest component should expect a call to `wasi:http/incoming-handler.handle` with a POST request to
+    /// "/foo" containing a single header "foo: bar" and a UTF-8 string body "Hello, SpinHttp!" and respond with a
+    /// 200 OK response that includes a single header "lorem: ipsum" and a UTF-8 string body "dolor sit amet".
+    pub inbound_http: Result<(), String>,
+
+    /// Result of the Spin variables test
+    ///
+    /// The guest component should expect a call according to [`crate::InvocationStyle`] with \["variables",
+    /// "foo"\] as arguments.  The component should call the host-implemented `variables::get` function with "foo"
+    /// as the argument and expect `ok("bar")` as the result.  The host will assert that said function is called
+    /// exactly once with the expected argument.
+    pub variables: Result<(), String>,
+
+    /// Result of the `wasi:http` outbound HTTP test
+    ///
+    /// The guest component should expect a call according to [`crate::InvocationStyle`] with \["http",
+    /// "http://127.0.0.1/test"\] as arguments.  The component should call the host-implemented
+    /// `wasi:http/outgoing-handler.handle` function with a GET request for the URL "http://127.0.0.1/test" and
+    /// expect a 200 response with the body "Jabberwocky".  The host will assert that said function is called
+    /// exactly once with a request for the specified URL.
+    pub http: Result<(), String>,
+
+    /// Results of the Spin key-value tests
+    ///
+    /// See [`KeyValueReport`] for details.
+    pub key_value: KeyValueReport,
+
+    /// Results of the Spin Redis tests
+    ///
+    /// See [`RedisReport`] for details.
+    pub redis: RedisReport,
+
+    /// Results of the Spin PostgreSQL tests
+    ///
+    /// See [`PostgresReport`] for details.
+    pub postgres: PostgresReport,
+
+    /// Results of the Spin MySql tests
+    ///
+    /// See [`MysqlReport`] for details.
+    pub mysql: MysqlReport,
+
+    /// Results of the Spin SQLite tests
+    ///
+    /// See [`SqliteReport`] for details.
+    pub sqlite: SqliteReport,
+}
+
+/// Run a test for each function of the `fermyon:spin/platform@2.0.0` world the specified `component` imports,
+/// returning the results as a [`Report`].
+pub(crate) async fn test(
+    component: &Component,
+    engine: &Engine,
+    test_config: TestConfig,
+) -> Result<Report> {
+    let mut linker = Linker::<Context>::new(engine);
+    wasmtime_wasi::preview2::command::add_to_linker(&mut linker)?;
+    wasmtime_wasi_http::proxy::add_only_http_to_linker(&mut linker)?;
+    variables::add_to_linker(&mut linker, |context| &mut context.v2.variables)?;
+    key_value::add_to_linker(&mut linker, |context| &mut context.v2.key_value)?;
+    redis::add_to_linker(&mut linker, |context| &mut context.v2.redis)?;
+    rdbms_types::add_to_linker(&mut linker, |context| &mut context.v2.postgres)?;
+    postgres::add_to_linker(&mut linker, |context| &mut context.v2.postgres)?;
+    mysql::add_to_linker(&mut linker, |context| &mut context.v2.mysql)?;
+    sqlite::add_to_linker(&mut linker, |context| &mut context.v2.sqlite)?;
+    llm::add_to_linker(&mut linker, |context| &mut context.v2.unsupported)?;
+
+    let pre = linker.instantiate_pre(component)?;
+
+    Ok(Report {
+        inbound_http: test_inbound_http::test(engine, test_config.clone(), &pre).await,
+        variables: test_variables::test(engine, test_config.clone(), &pre).await,
+        http: test_http::test(engine, test_config.clone(), &pre).await,
+        key_value: test_key_value::test(engine, test_config.clone(), &pre).await?,
+        redis: test_redis::test(engine, test_config.clone(), &pre).await?,
+        postgres: test_postgres::test(engine, test_config.clone(), &pre).await?,
+        mysql: test_mysql::test(engine, test_config.clone(), &pre).await?,
+        sqlite: test_sqlite::test(engine, test_config, &pre).await?,
+    })
+}
+
+/// Send `request` to the `wasi:http/incoming-handler@0.2.0` export of `instance`, returning the response with its
+/// body read in full
+pub(crate) async fn handle_request(
+    store: &mut Store<Context>,
+    instance: &Instance,
+    request: hyper::Request<Bytes>,
+) -> Result<hyper::Response<Bytes>> {
+    let func = instance
+        .exports(&mut *store)
+        .instance("wasi:http/incoming-handler@0.2.0")
+        .ok_or_else(|| anyhow!("no wasi:http/incoming-handler@0.2.0 instance found"))?
+        .typed_func::<(
+            Resource<HostIncomingRequest>,
+            Resource<HostResponseOutparam>,
+        ), ()>("handle")?;
+
+    let (sender, receiver) = oneshot::channel();
+    let request = store.data_mut().new_incoming_request(
+        request.map(|body| Full::new(body).map_err(|never| match never {}).boxed()),
+    )?;
+    let response_out = store.data_mut().new_response_outparam(sender)?;
+
+    // The body is read while the guest is still running, since it may not finish writing it before the host
+    // starts reading
+    let ((), response) = tokio::try_join!(
+        func.call_async(&mut *store, (request, response_out)),
+        async {
+            let response = receiver
+                .await
+                .context("guest did not set a response")?
+                .map_err(|e| anyhow!("guest responded with an error: {e:?}"))?;
+            let (parts, body) = response.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| anyhow!("failed to read response body: {e:?}"))?
+                .to_bytes();
+            Ok::<_, anyhow::Error>(hyper::Response::from_parts(parts, body))
+        }
+    )?;
+
+    Ok(response)
+}
+
+/// Host state for the `fermyon:spin/platform@2.0.0` interfaces
+pub(crate) struct Platform {
+    wasi_http: WasiHttpCtx,
+    variables: Variables,
+    http: Http,
+    key_value: KeyValue,
+    redis: Redis,
+    postgres: Postgres,
+    mysql: Mysql,
+    sqlite: Sqlite,
+    unsupported: Unsupported,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self {
+            wasi_http: WasiHttpCtx,
+
+            variables: Default::default(),
+            http: Default::default(),
+            key_value: Default::default(),
+            redis: Default::default(),
+            postgres: Default::default(),
+            mysql: Default::default(),
+            sqlite: Default::default(),
+            unsupported: Default::default(),
+        }
+    }
+}
+
+impl WasiHttpView for Context {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.v2.wasi_http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn send_request(
+        &mut self,
+        request: OutgoingRequest,
+    ) -> Result<Resource<HostFutureIncomingResponse>> {
+        let between_bytes_timeout = request.between_bytes_timeout;
+        let response = self
+            .v2
+            .http
+            .send_request(request)
+            .map(|body| IncomingResponseInternal {
+                resp: hyper::Response::new(
+                    Full::new(Bytes::from(body))
+                        .map_err(|never| match never {})
+                        .boxed(),
+                ),
+                worker: Arc::new(preview2::spawn(async {})),
+                between_bytes_timeout,
+            });
+
+        let future = HostFutureIncomingResponse::new(preview2::spawn(async move { Ok(response) }));
+        Ok(self.table.push(future)?)
+    }
+}
+
+/// Implements the interfaces of the world which this suite does not cover, so that components importing them can
+/// still be instantiated
+#[derive(Default)]
+struct Unsupported;
+
+#[async_trait]
+impl llm::Host for Unsupported {
+    async fn infer(
+        &mut self,
+        _model: llm::InferencingModel,
+        _prompt: String,
+        _params: Option<llm::InferencingParams>,
+    ) -> Result<Result<llm::InferencingResult, llm::Error>> {
+        Ok(Err(llm::Error::ModelNotSupported))
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        _model: llm::EmbeddingModel,
+        _text: Vec<String>,
+    ) -> Result<Result<llm::EmbeddingsResult, llm::Error>> {
+        Ok(Err(llm::Error::ModelNotSupported))
+    }
+}
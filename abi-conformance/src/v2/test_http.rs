@@ -0,0 +1,60 @@
+use crate::{Context, TestConfig};
+use anyhow::{ensure, Result};
+use std::collections::HashMap;
+use wasmtime::{component::InstancePre, Engine};
+use wasmtime_wasi_http::{bindings::http::types::ErrorCode, types::OutgoingRequest};
+
+#[derive(Default)]
+pub(super) struct Http {
+    map: HashMap<String, String>,
+}
+
+impl Http {
+    /// Record a request sent via `wasi:http/outgoing-handler`, returning the body of the response to send back
+    pub(super) fn send_request(&mut self, request: OutgoingRequest) -> Result<String, ErrorCode> {
+        let scheme = if request.use_tls { "https" } else { "http" };
+        let default_port = if request.use_tls { ":443" } else { ":80" };
+        let authority = request
+            .authority
+            .strip_suffix(default_port)
+            .unwrap_or(&request.authority);
+        let path = request
+            .request
+            .uri()
+            .path_and_query()
+            .map_or("/", |path| path.as_str());
+
+        self.map
+            .remove(&format!("{scheme}://{authority}{path}"))
+            .ok_or(ErrorCode::HttpRequestDenied)
+    }
+}
+
+pub(super) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context
+            .v2
+            .http
+            .map
+            .insert("http://127.0.0.1/test".into(), "Jabberwocky".into());
+    });
+
+    crate::run_command(
+        &mut store,
+        pre,
+        &["http", "http://127.0.0.1/test"],
+        |store| {
+            ensure!(
+                store.data().v2.http.map.is_empty(),
+                "expected module to call `wasi:http/outgoing-handler.handle` exactly once"
+            );
+
+            Ok(())
+        },
+    )
+    .await
+}
@@ -0,0 +1,52 @@
+use crate::{Context, TestConfig};
+use anyhow::ensure;
+use bytes::Bytes;
+use wasmtime::{component::InstancePre, Engine};
+
+pub(crate) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    crate::run(async {
+        let mut store = crate::create_store(engine, test_config);
+        let instance = pre.instantiate_async(&mut store).await?;
+
+        let response = super::handle_request(
+            &mut store,
+            &instance,
+            hyper::Request::post("http://localhost/foo")
+                .header("foo", "bar")
+                .body(Bytes::from_static(b"Hello, SpinHttp!"))?,
+        )
+        .await?;
+
+        ensure!(
+            response.status() == 200,
+            "expected response status 200, got {} (body: {:?})",
+            response.status(),
+            String::from_utf8_lossy(response.body())
+        );
+
+        ensure!(
+            response.headers().len() == 1
+                && response
+                    .headers()
+                    .get("lorem")
+                    .map_or(false, |value| value == "ipsum"),
+            "expected a single response header, \"lorem: ipsum\", got {:?}",
+            response.headers()
+        );
+
+        let expected_body = "dolor sit amet";
+
+        ensure!(
+            response.body() == expected_body.as_bytes(),
+            "expected a response body containing the string {expected_body:?}, got {:?}",
+            String::from_utf8_lossy(response.body())
+        );
+
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,335 @@
+use super::key_value::{self, Error, Store};
+use crate::{Context, TestConfig};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+use wasmtime::{
+    component::{InstancePre, Resource},
+    Engine,
+};
+
+/// Report of which key-value functions a module successfully used, if any
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct KeyValueReport {
+    /// Result of the key-value `store.open` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["key-value-open",
+    /// "foo"\] as arguments.  The module should call the host-implemented `key-value.store.open` function with "foo"
+    /// as the argument and expect `ok(store)` as the result.  The host will assert that said function is called
+    /// exactly once with the expected argument.
+    pub open: Result<(), String>,
+
+    /// Result of the key-value `store.get` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["key-value-store-get", "foo", "bar"\] as arguments.  The module should open the store "foo" and call the
+    /// host-implemented `key-value.store.get` function on it with "bar" as the argument and expect `ok(some("baz"))`
+    /// (UTF-8-encoded) as the result.  The host will assert that said function is called exactly once with the
+    /// expected arguments.
+    pub get: Result<(), String>,
+
+    /// Result of the key-value `store.set` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["key-value-store-set", "foo", "bar", "baz"\] as arguments.  The module should open the store "foo" and call
+    /// the host-implemented `key-value.store.set` function on it with the arguments \["bar", "baz"\] (the latter
+    /// UTF-8-encoded) and expect `ok(unit)` as the result.  The host will assert that said function is called
+    /// exactly once with the expected arguments.
+    pub set: Result<(), String>,
+
+    /// Result of the key-value `store.delete` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["key-value-store-delete", "foo", "bar"\] as arguments.  The module should open the store "foo" and call the
+    /// host-implemented `key-value.store.delete` function on it with "bar" as the argument and expect `ok(unit)` as
+    /// the result.  The host will assert that said function is called exactly once with the expected arguments.
+    pub delete: Result<(), String>,
+
+    /// Result of the key-value `store.exists` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["key-value-store-exists", "foo", "bar"\] as arguments.  The module should open the store "foo" and call the
+    /// host-implemented `key-value.store.exists` function on it with "bar" as the argument and expect `ok(true)` as
+    /// the result.  The host will assert that said function is called exactly once with the expected arguments.
+    pub exists: Result<(), String>,
+
+    /// Result of the key-value `store.get-keys` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with
+    /// \["key-value-store-get-keys", "foo"\] as arguments.  The module should open the store "foo" and call the
+    /// host-implemented `key-value.store.get-keys` function on it and expect `ok(list("bar", "baz"))` as the
+    /// result.  The host will assert that said function is called exactly once.
+    pub get_keys: Result<(), String>,
+}
+
+#[derive(Default)]
+pub(super) struct KeyValue {
+    open_map: HashMap<String, u32>,
+    get_map: HashMap<(u32, String), Vec<u8>>,
+    set_set: HashSet<(u32, String, Vec<u8>)>,
+    delete_set: HashSet<(u32, String)>,
+    exists_map: HashMap<(u32, String), bool>,
+    get_keys_map: HashMap<u32, Vec<String>>,
+}
+
+#[async_trait]
+impl key_value::HostStore for KeyValue {
+    async fn open(&mut self, label: String) -> Result<Result<Resource<Store>, Error>> {
+        Ok(self
+            .open_map
+            .remove(&label)
+            .map(Resource::new_own)
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "expected {:?}, got {:?}",
+                    self.open_map.keys(),
+                    label
+                ))
+            }))
+    }
+
+    async fn get(
+        &mut self,
+        store: Resource<Store>,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, Error>> {
+        let store = store.rep();
+        Ok(self
+            .get_map
+            .remove(&(store, key.to_owned()))
+            .map(Some)
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "expected {:?}, got {:?}",
+                    self.get_map.keys(),
+                    iter::once(&(store, key))
+                ))
+            }))
+    }
+
+    async fn set(
+        &mut self,
+        store: Resource<Store>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        let store = store.rep();
+        Ok(
+            if self
+                .set_set
+                .remove(&(store, key.to_owned(), value.to_vec()))
+            {
+                Ok(())
+            } else {
+                Err(Error::Other(format!(
+                    "expected {:?}, got {:?}",
+                    self.set_set.iter(),
+                    iter::once(&(store, key, value))
+                )))
+            },
+        )
+    }
+
+    async fn delete(&mut self, store: Resource<Store>, key: String) -> Result<Result<(), Error>> {
+        let store = store.rep();
+        Ok(if self.delete_set.remove(&(store, key.to_owned())) {
+            Ok(())
+        } else {
+            Err(Error::Other(format!(
+                "expected {:?}, got {:?}",
+                self.delete_set.iter(),
+                iter::once(&(store, key))
+            )))
+        })
+    }
+
+    async fn exists(&mut self, store: Resource<Store>, key: String) -> Result<Result<bool, Error>> {
+        let store = store.rep();
+        Ok(self
+            .exists_map
+            .remove(&(store, key.to_owned()))
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "expected {:?}, got {:?}",
+                    self.exists_map.keys(),
+                    iter::once(&(store, key))
+                ))
+            }))
+    }
+
+    async fn get_keys(&mut self, store: Resource<Store>) -> Result<Result<Vec<String>, Error>> {
+        let store = store.rep();
+        Ok(self.get_keys_map.remove(&store).ok_or_else(|| {
+            Error::Other(format!(
+                "expected {:?}, got {:?}",
+                self.get_keys_map.keys(),
+                iter::once(&store)
+            ))
+        }))
+    }
+
+    fn drop(&mut self, _store: Resource<Store>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl key_value::Host for KeyValue {}
+
+pub(super) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<KeyValueReport> {
+    Ok(KeyValueReport {
+        open: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.key_value.open_map.insert("foo".into(), 42);
+                });
+
+            crate::run_command(&mut store, pre, &["key-value-open", "foo"], |store| {
+                ensure!(
+                    store.data().v2.key_value.open_map.is_empty(),
+                    "expected module to call `key-value.store.open` exactly once"
+                );
+
+                Ok(())
+            })
+            .await
+        },
+
+        get: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.key_value.open_map.insert("foo".into(), 42);
+                    context
+                        .v2
+                        .key_value
+                        .get_map
+                        .insert((42, "bar".into()), b"baz".to_vec());
+                });
+
+            crate::run_command(
+                &mut store,
+                pre,
+                &["key-value-store-get", "foo", "bar"],
+                |store| {
+                    ensure!(
+                        store.data().v2.key_value.get_map.is_empty(),
+                        "expected module to call `key-value.store.get` exactly once"
+                    );
+
+                    Ok(())
+                },
+            )
+            .await
+        },
+
+        set: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.key_value.open_map.insert("foo".into(), 42);
+                    context
+                        .v2
+                        .key_value
+                        .set_set
+                        .insert((42, "bar".into(), b"baz".to_vec()));
+                });
+
+            crate::run_command(
+                &mut store,
+                pre,
+                &["key-value-store-set", "foo", "bar", "baz"],
+                |store| {
+                    ensure!(
+                        store.data().v2.key_value.set_set.is_empty(),
+                        "expected module to call `key-value.store.set` exactly once"
+                    );
+
+                    Ok(())
+                },
+            )
+            .await
+        },
+
+        delete: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.key_value.open_map.insert("foo".into(), 42);
+                    context.v2.key_value.delete_set.insert((42, "bar".into()));
+                });
+
+            crate::run_command(
+                &mut store,
+                pre,
+                &["key-value-store-delete", "foo", "bar"],
+                |store| {
+                    ensure!(
+                        store.data().v2.key_value.delete_set.is_empty(),
+                        "expected module to call `key-value.store.delete` exactly once"
+                    );
+
+                    Ok(())
+                },
+            )
+            .await
+        },
+
+        exists: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.key_value.open_map.insert("foo".into(), 42);
+                    context
+                        .v2
+                        .key_value
+                        .exists_map
+                        .insert((42, "bar".into()), true);
+                });
+
+            crate::run_command(
+                &mut store,
+                pre,
+                &["key-value-store-exists", "foo", "bar"],
+                |store| {
+                    ensure!(
+                        store.data().v2.key_value.exists_map.is_empty(),
+                        "expected module to call `key-value.store.exists` exactly once"
+                    );
+
+                    Ok(())
+                },
+            )
+            .await
+        },
+
+        get_keys: {
+            let mut store = crate::create_store_with_context(engine, test_config, |context| {
+                context.v2.key_value.open_map.insert("foo".into(), 42);
+                context
+                    .v2
+                    .key_value
+                    .get_keys_map
+                    .insert(42, vec!["bar".into(), "baz".into()]);
+            });
+
+            crate::run_command(
+                &mut store,
+                pre,
+                &["key-value-store-get-keys", "foo"],
+                |store| {
+                    ensure!(
+                        store.data().v2.key_value.get_keys_map.is_empty(),
+                        "expected module to call `key-value.store.get-keys` exactly once"
+                    );
+
+                    Ok(())
+                },
+            )
+            .await
+        },
+    })
+}
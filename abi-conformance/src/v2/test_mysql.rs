@@ -0,0 +1,213 @@
+use super::{
+    mysql::{self, Connection},
+    rdbms_types::{Column, DbDataType, DbValue, Error, ParameterValue, RowSet},
+};
+use crate::{Context, TestConfig};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+use wasmtime::{
+    component::{InstancePre, Resource},
+    Engine,
+};
+
+/// Report of which MySQL functions a module successfully used, if any
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct MysqlReport {
+    /// Result of the MySQL `connection.open` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["mysql-open",
+    /// "127.0.0.1"\] as arguments.  The module should call the host-implemented `mysql.connection.open`
+    /// function with "127.0.0.1" as the argument and expect `ok(connection)` as the result.  The host will assert
+    /// that said function is called exactly once with the expected argument.
+    pub open: Result<(), String>,
+
+    /// Result of the MySQL statement execution test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["mysql-execute",
+    /// "127.0.0.1", "INSERT INTO foo (x) VALUES ($1)", "int8:42"\] as arguments.  The module should open a
+    /// connection to "127.0.0.1" and call the host-implemented `mysql.connection.execute` function on it with
+    /// the arguments \["INSERT INTO foo (x) VALUES ($1)", `\[int8(42)\]`\] and expect `ok(unit)` as the result.  The
+    /// host will assert that said function is called exactly once with the specified arguments.
+    pub execute: Result<(), String>,
+
+    /// Result of the MySQL query execution test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["mysql-query",
+    /// "127.0.0.1", "SELECT x FROM foo"\] as arguments.  The module should open a connection to "127.0.0.1" and
+    /// call the host-implemented `mysql.connection.query` function on it with the arguments \["SELECT x FROM
+    /// foo", `\[\]`\] and expect `ok({ columns: \[ { name: "x", data-type: int8 } \], rows: \[ \[ int8(42) \] \]})`
+    /// as the result.  The host will assert that said function is called exactly once with the specified arguments.
+    pub query: Result<(), String>,
+}
+
+#[derive(Default)]
+pub(super) struct Mysql {
+    open_map: HashMap<String, u32>,
+    execute_set: HashSet<(u32, String, String)>,
+    query_map: HashMap<(u32, String, String), RowSet>,
+}
+
+#[async_trait]
+impl mysql::HostConnection for Mysql {
+    async fn open(&mut self, address: String) -> Result<Result<Resource<Connection>, Error>> {
+        Ok(self
+            .open_map
+            .remove(&address)
+            .map(Resource::new_own)
+            .ok_or_else(|| {
+                Error::ConnectionFailed(format!(
+                    "expected {:?}, got {:?}",
+                    self.open_map.keys(),
+                    address
+                ))
+            }))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<(), Error>> {
+        let key = (connection.rep(), statement, format!("{params:?}"));
+        Ok(if self.execute_set.remove(&key) {
+            Ok(())
+        } else {
+            Err(Error::Other(format!(
+                "expected {:?}, got {:?}",
+                self.execute_set.iter(),
+                iter::once(&key)
+            )))
+        })
+    }
+
+    async fn query(
+        &mut self,
+        connection: Resource<Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<RowSet, Error>> {
+        let key = (connection.rep(), statement, format!("{params:?}"));
+        Ok(self.query_map.remove(&key).ok_or_else(|| {
+            Error::Other(format!(
+                "expected {:?}, got {:?}",
+                self.query_map.keys(),
+                iter::once(&key)
+            ))
+        }))
+    }
+
+    fn drop(&mut self, _connection: Resource<Connection>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl mysql::Host for Mysql {}
+
+pub(super) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<MysqlReport> {
+    Ok(MysqlReport {
+        open: {
+            let mut store =
+                crate::create_store_with_context(engine, test_config.clone(), |context| {
+                    context.v2.mysql.open_map.insert("127.0.0.1".into(), 42);
+                });
+
+            crate::run_command(&mut store, pre, &["mysql-open", "127.0.0.1"], |store| {
+                ensure!(
+                    store.data().v2.mysql.open_map.is_empty(),
+                    "expected module to call `mysql.connection.open` exactly once"
+                );
+
+                Ok(())
+            })
+            .await
+        },
+        execute: test_execute(engine, test_config.clone(), pre).await,
+        query: test_query(engine, test_config, pre).await,
+    })
+}
+
+async fn test_execute(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.v2.mysql.open_map.insert("127.0.0.1".into(), 42);
+        context.v2.mysql.execute_set.insert((
+            42,
+            "INSERT INTO foo (x) VALUES ($1)".into(),
+            format!("{:?}", vec![ParameterValue::Int8(42)]),
+        ));
+    });
+
+    crate::run_command(
+        &mut store,
+        pre,
+        &[
+            "mysql-execute",
+            "127.0.0.1",
+            "INSERT INTO foo (x) VALUES ($1)",
+            "int8:42",
+        ],
+        |store| {
+            ensure!(
+                store.data().v2.mysql.execute_set.is_empty(),
+                "expected module to call `mysql.connection.execute` exactly once"
+            );
+
+            Ok(())
+        },
+    )
+    .await
+}
+
+async fn test_query(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    let row_set = RowSet {
+        columns: vec![Column {
+            name: "x".into(),
+            data_type: DbDataType::Int8,
+        }],
+        rows: vec![vec![DbValue::Int8(42)]],
+    };
+
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.v2.mysql.open_map.insert("127.0.0.1".into(), 42);
+        context.v2.mysql.query_map.insert(
+            (
+                42,
+                "SELECT x FROM foo".into(),
+                format!("{:?}", Vec::<()>::new()),
+            ),
+            row_set,
+        );
+    });
+
+    crate::run_command(
+        &mut store,
+        pre,
+        &["mysql-query", "127.0.0.1", "SELECT x FROM foo"],
+        |store| {
+            ensure!(
+                store.data().v2.mysql.query_map.is_empty(),
+                "expected module to call `mysql.connection.query` exactly once"
+            );
+
+            Ok(())
+        },
+    )
+    .await
+}
@@ -0,0 +1,443 @@
+use super::redis::{self, Connection, Error, RedisParameter, RedisResult};
+use crate::{Context, TestConfig};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
+use wasmtime::{
+    component::{InstancePre, Resource},
+    Engine,
+};
+
+/// Report of which Redis tests succeeded or failed
+///
+/// Apart from `open`, each test expects the guest to open a connection to "127.0.0.1" and then call the method
+/// under test on it.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct RedisReport {
+    /// Result of the Redis `connection.open` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-open",
+    /// "127.0.0.1"\] as arguments.  The module should call the host-implemented `redis.connection.open` function
+    /// with "127.0.0.1" as the argument and expect `ok(connection)` as the result.  The host will assert that said
+    /// function is called exactly once with the expected argument.
+    pub open: Result<(), String>,
+
+    /// Result of the Redis `PUBLISH` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-publish",
+    /// "127.0.0.1", "foo", "bar"\] as arguments.  The module should call the host-implemented
+    /// `redis.connection.publish` function with the arguments \["foo", "bar"\] and expect `ok(unit)` as the
+    /// result.  The host will assert that said function is called exactly once with the specified arguments.
+    pub publish: Result<(), String>,
+
+    /// Result of the Redis `SET` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-set",
+    /// "127.0.0.1", "foo", "bar"\] as arguments.  The module should call the host-implemented
+    /// `redis.connection.set` function with the arguments \["foo", "bar"\] and expect `ok(unit)` as the result.
+    /// The host will assert that said function is called exactly once with the specified arguments.
+    pub set: Result<(), String>,
+
+    /// Result of the Redis `GET` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-get",
+    /// "127.0.0.1", "foo"\] as arguments.  The module should call the host-implemented `redis.connection.get`
+    /// function with "foo" as the argument and expect `ok(some("bar"))` (UTF-8-encoded) as the result.  The host
+    /// will assert that said function is called exactly once with the specified argument.
+    pub get: Result<(), String>,
+
+    /// Result of the Redis `INCR` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-incr",
+    /// "127.0.0.1", "foo"\] as arguments.  The module should call the host-implemented `redis.connection.incr`
+    /// function with "foo" as the argument and expect `ok(42)` as the result.  The host will assert that said
+    /// function is called exactly once with the specified argument.
+    pub incr: Result<(), String>,
+
+    /// Result of the Redis `DEL` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-del",
+    /// "127.0.0.1", "foo"\] as arguments.  The module should call the host-implemented `redis.connection.del`
+    /// function with \["foo"\] as the argument and expect `ok(0)` as the result.  The host will assert that said
+    /// function is called exactly once with the specified argument.
+    pub del: Result<(), String>,
+
+    /// Result of the Redis `SADD` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-sadd",
+    /// "127.0.0.1", "foo", "bar", "baz"\] as arguments.  The module should call the host-implemented
+    /// `redis.connection.sadd` function with the arguments \["foo", \["bar", "baz"\]\] and expect `ok(2)` as the
+    /// result.  The host will assert that said function is called exactly once with the specified arguments.
+    pub sadd: Result<(), String>,
+
+    /// Result of the Redis `SREM` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-srem",
+    /// "127.0.0.1", "foo", "bar", "baz"\] as arguments.  The module should call the host-implemented
+    /// `redis.connection.srem` function with the arguments \["foo", \["bar", "baz"\]\] and expect `ok(2)` as the
+    /// result.  The host will assert that said function is called exactly once with the specified arguments.
+    pub srem: Result<(), String>,
+
+    /// Result of the Redis `SMEMBERS` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-smembers",
+    /// "127.0.0.1", "foo"\] as arguments.  The module should call the host-implemented `redis.connection.smembers`
+    /// function with "foo" as the argument and expect `ok(list("bar", "baz"))` as the result.  The host will assert
+    /// that said function is called exactly once with the specified argument.
+    pub smembers: Result<(), String>,
+
+    /// Result of the Redis `execute` test
+    ///
+    /// The guest module should expect a call according to [`crate::InvocationStyle`] with \["redis-execute",
+    /// "127.0.0.1", "append", "foo", "baz"\] as arguments.  The module should call the host-implemented
+    /// `redis.connection.execute` function with the arguments \["append", \[binary("foo"), binary("baz")\]\] and
+    /// expect `ok(list(int64(3)))` as the result.  The host will assert that said function is called exactly once
+    /// with the specified arguments.
+    pub execute: Result<(), String>,
+}
+
+#[derive(Default)]
+pub(super) struct Redis {
+    open_map: HashMap<String, u32>,
+    publish_set: HashSet<(u32, String, Vec<u8>)>,
+    set_set: HashSet<(u32, String, Vec<u8>)>,
+    get_map: HashMap<(u32, String), Vec<u8>>,
+    incr_map: HashMap<(u32, String), i64>,
+    del_map: HashMap<(u32, Vec<String>), u32>,
+    sadd_map: HashMap<(u32, String, Vec<String>), u32>,
+    srem_map: HashMap<(u32, String, Vec<String>), u32>,
+    smembers_map: HashMap<(u32, String), Vec<String>>,
+    execute_map: HashMap<(u32, String, Vec<Vec<u8>>), Vec<RedisResult>>,
+}
+
+fn unexpected<'a, K: Debug + 'a>(expected: impl Iterator<Item = &'a K>, got: K) -> Error {
+    Error::Other(format!(
+        "expected {:?}, got {got:?}",
+        expected.collect::<Vec<_>>()
+    ))
+}
+
+#[async_trait]
+impl redis::HostConnection for Redis {
+    async fn open(&mut self, address: String) -> Result<Result<Resource<Connection>, Error>> {
+        Ok(self
+            .open_map
+            .remove(&address)
+            .map(Resource::new_own)
+            .ok_or_else(|| unexpected(self.open_map.keys(), address)))
+    }
+
+    async fn publish(
+        &mut self,
+        connection: Resource<Connection>,
+        channel: String,
+        payload: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        let key = (connection.rep(), channel, payload);
+        Ok(if self.publish_set.remove(&key) {
+            Ok(())
+        } else {
+            Err(unexpected(self.publish_set.iter(), key))
+        })
+    }
+
+    async fn get(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, Error>> {
+        let key = (connection.rep(), key);
+        Ok(self
+            .get_map
+            .remove(&key)
+            .map(Some)
+            .ok_or_else(|| unexpected(self.get_map.keys(), key)))
+    }
+
+    async fn set(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        let key = (connection.rep(), key, value);
+        Ok(if self.set_set.remove(&key) {
+            Ok(())
+        } else {
+            Err(unexpected(self.set_set.iter(), key))
+        })
+    }
+
+    async fn incr(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+    ) -> Result<Result<i64, Error>> {
+        let key = (connection.rep(), key);
+        Ok(self
+            .incr_map
+            .remove(&key)
+            .map(|value| value + 1)
+            .ok_or_else(|| unexpected(self.incr_map.keys(), key)))
+    }
+
+    async fn del(
+        &mut self,
+        connection: Resource<Connection>,
+        keys: Vec<String>,
+    ) -> Result<Result<u32, Error>> {
+        let key = (connection.rep(), keys);
+        Ok(self
+            .del_map
+            .remove(&key)
+            .ok_or_else(|| unexpected(self.del_map.keys(), key)))
+    }
+
+    async fn sadd(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<Result<u32, Error>> {
+        let key = (connection.rep(), key, values);
+        Ok(self
+            .sadd_map
+            .remove(&key)
+            .ok_or_else(|| unexpected(self.sadd_map.keys(), key)))
+    }
+
+    async fn smembers(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+    ) -> Result<Result<Vec<String>, Error>> {
+        let key = (connection.rep(), key);
+        Ok(self
+            .smembers_map
+            .remove(&key)
+            .ok_or_else(|| unexpected(self.smembers_map.keys(), key)))
+    }
+
+    async fn srem(
+        &mut self,
+        connection: Resource<Connection>,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<Result<u32, Error>> {
+        let key = (connection.rep(), key, values);
+        Ok(self
+            .srem_map
+            .remove(&key)
+            .ok_or_else(|| unexpected(self.srem_map.keys(), key)))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<Connection>,
+        command: String,
+        arguments: Vec<RedisParameter>,
+    ) -> Result<Result<Vec<RedisResult>, Error>> {
+        let key = (
+            connection.rep(),
+            command,
+            arguments
+                .into_iter()
+                .filter_map(|v| {
+                    if let RedisParameter::Binary(bytes) = v {
+                        Some(bytes)
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+        );
+        Ok(self
+            .execute_map
+            .remove(&key)
+            .ok_or_else(|| unexpected(self.execute_map.keys(), key)))
+    }
+
+    fn drop(&mut self, _connection: Resource<Connection>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl redis::Host for Redis {}
+
+pub(super) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<RedisReport> {
+    Ok(RedisReport {
+        open: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-open", "127.0.0.1"],
+            "open",
+            |_| {},
+            |redis| redis.open_map.is_empty(),
+        )
+        .await,
+
+        publish: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-publish", "127.0.0.1", "foo", "bar"],
+            "publish",
+            |redis| {
+                redis
+                    .publish_set
+                    .insert((42, "foo".into(), b"bar".to_vec()));
+            },
+            |redis| redis.publish_set.is_empty(),
+        )
+        .await,
+
+        set: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-set", "127.0.0.1", "foo", "bar"],
+            "set",
+            |redis| {
+                redis.set_set.insert((42, "foo".into(), b"bar".to_vec()));
+            },
+            |redis| redis.set_set.is_empty(),
+        )
+        .await,
+
+        get: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-get", "127.0.0.1", "foo"],
+            "get",
+            |redis| {
+                redis.get_map.insert((42, "foo".into()), b"bar".to_vec());
+            },
+            |redis| redis.get_map.is_empty(),
+        )
+        .await,
+
+        incr: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-incr", "127.0.0.1", "foo"],
+            "incr",
+            |redis| {
+                redis.incr_map.insert((42, "foo".into()), 41);
+            },
+            |redis| redis.incr_map.is_empty(),
+        )
+        .await,
+
+        del: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-del", "127.0.0.1", "foo"],
+            "del",
+            |redis| {
+                redis.del_map.insert((42, vec!["foo".into()]), 0);
+            },
+            |redis| redis.del_map.is_empty(),
+        )
+        .await,
+
+        sadd: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-sadd", "127.0.0.1", "foo", "bar", "baz"],
+            "sadd",
+            |redis| {
+                redis
+                    .sadd_map
+                    .insert((42, "foo".into(), vec!["bar".into(), "baz".into()]), 2);
+            },
+            |redis| redis.sadd_map.is_empty(),
+        )
+        .await,
+
+        srem: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-srem", "127.0.0.1", "foo", "bar", "baz"],
+            "srem",
+            |redis| {
+                redis
+                    .srem_map
+                    .insert((42, "foo".into(), vec!["bar".into(), "baz".into()]), 2);
+            },
+            |redis| redis.srem_map.is_empty(),
+        )
+        .await,
+
+        smembers: test_method(
+            engine,
+            test_config.clone(),
+            pre,
+            &["redis-smembers", "127.0.0.1", "foo"],
+            "smembers",
+            |redis| {
+                redis
+                    .smembers_map
+                    .insert((42, "foo".into()), vec!["bar".into(), "baz".into()]);
+            },
+            |redis| redis.smembers_map.is_empty(),
+        )
+        .await,
+
+        execute: test_method(
+            engine,
+            test_config,
+            pre,
+            &["redis-execute", "127.0.0.1", "append", "foo", "baz"],
+            "execute",
+            |redis| {
+                redis.execute_map.insert(
+                    (42, "append".into(), vec![b"foo".to_vec(), b"baz".to_vec()]),
+                    vec![RedisResult::Int64(3)],
+                );
+            },
+            |redis| redis.execute_map.is_empty(),
+        )
+        .await,
+    })
+}
+
+/// Run the command given by `arguments` with a connection to "127.0.0.1" available to open and the expectations
+/// added by `expect`, then use `called` to check that the module called `redis.connection.<method>` as expected
+async fn test_method(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+    arguments: &[&str],
+    method: &str,
+    expect: impl FnOnce(&mut Redis),
+    called: impl FnOnce(&Redis) -> bool,
+) -> Result<(), String> {
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.v2.redis.open_map.insert("127.0.0.1".into(), 42);
+        expect(&mut context.v2.redis);
+    });
+
+    crate::run_command(&mut store, pre, arguments, |store| {
+        ensure!(
+            called(&store.data().v2.redis),
+            "expected module to call `redis.connection.{method}` exactly once"
+        );
+
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,40 @@
+use super::variables::{self, Error};
+use crate::{Context, TestConfig};
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use wasmtime::{component::InstancePre, Engine};
+
+#[derive(Default)]
+pub(super) struct Variables {
+    map: HashMap<String, String>,
+}
+
+#[async_trait]
+impl variables::Host for Variables {
+    async fn get(&mut self, name: String) -> Result<Result<String, Error>> {
+        Ok(self.map.remove(&name).ok_or_else(|| {
+            Error::Undefined(format!("expected {:?}, got {:?}", self.map.keys(), name))
+        }))
+    }
+}
+
+pub(super) async fn test(
+    engine: &Engine,
+    test_config: TestConfig,
+    pre: &InstancePre<Context>,
+) -> Result<(), String> {
+    let mut store = crate::create_store_with_context(engine, test_config, |context| {
+        context.v2.variables.map.insert("foo".into(), "bar".into());
+    });
+
+    crate::run_command(&mut store, pre, &["variables", "foo"], |store| {
+        ensure!(
+            store.data().v2.variables.map.is_empty(),
+            "expected module to call `variables::get` exactly once"
+        );
+
+        Ok(())
+    })
+    .await
+}
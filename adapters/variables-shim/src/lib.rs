@@ -0,0 +1,36 @@
+//! Exports `fermyon:spin/config` by forwarding each lookup to an imported `fermyon:spin/variables@2.0.0`, which is
+//! how `spin_componentize::SpinWorld::V2_0_0` wraps wit-bindgen 0.2 modules.
+
+use exports::fermyon::spin::config::{self, Error};
+use fermyon::spin2_0_0::variables;
+
+wit_bindgen::generate!({
+    inline: "
+        package spin-componentize:variables-shim;
+
+        world shim {
+            import fermyon:spin/variables@2.0.0;
+            export fermyon:spin/config;
+        }
+    ",
+    world: "spin-componentize:variables-shim/shim",
+    path: "../../wit",
+    exports: {
+        "fermyon:spin/config": Shim,
+    },
+});
+
+struct Shim;
+
+impl config::Guest for Shim {
+    fn get_config(key: String) -> Result<String, Error> {
+        variables::get(&key).map_err(|e| match e {
+            // `config` documents keys not defined in the manifest as invalid, so both cases map to `invalid-key`
+            variables::Error::InvalidName(message) | variables::Error::Undefined(message) => {
+                Error::InvalidKey(message)
+            }
+            variables::Error::Provider(message) => Error::Provider(message),
+            variables::Error::Other(message) => Error::Other(message),
+        })
+    }
+}
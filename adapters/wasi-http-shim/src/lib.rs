@@ -0,0 +1,123 @@
+//! Exports `wasi:http/incoming-handler@0.2.0` by forwarding each request to an imported
+//! `fermyon:spin/inbound-http`, which is how `spin_componentize::SpinWorld::V2_0_0` wraps wit-bindgen 0.2 modules.
+
+use exports::wasi::http0_2_0::incoming_handler;
+use fermyon::spin::{
+    http_types::{Method, Request, Response},
+    inbound_http,
+};
+use wasi::{
+    http0_2_0::types::{
+        self as http_types, Fields, IncomingRequest, OutgoingBody, OutgoingResponse,
+        ResponseOutparam,
+    },
+    io0_2_0::streams::{InputStream, StreamError},
+};
+
+wit_bindgen::generate!({
+    inline: "
+        package spin-componentize:wasi-http-shim;
+
+        world shim {
+            import fermyon:spin/inbound-http;
+            export wasi:http/incoming-handler@0.2.0;
+        }
+    ",
+    world: "spin-componentize:wasi-http-shim/shim",
+    path: "../../wit",
+    exports: {
+        "wasi:http/incoming-handler@0.2.0": Shim,
+    },
+});
+
+struct Shim;
+
+impl incoming_handler::Guest for Shim {
+    fn handle(request: IncomingRequest, response_out: ResponseOutparam) {
+        let response = match to_spin_request(request) {
+            Some(request) => inbound_http::handle_request(&request),
+            // The Spin 1 HTTP trigger has no way of passing any other method to the module
+            None => Response {
+                status: 405,
+                headers: None,
+                body: None,
+            },
+        };
+
+        let headers = response
+            .headers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, value)| (name, value.into_bytes()))
+            .collect::<Vec<_>>();
+        // Headers which WASI HTTP refuses (e.g. `connection`) are dropped rather than failing the whole response
+        let fields = Fields::from_list(&headers).unwrap_or_else(|_| Fields::new());
+        let outgoing = OutgoingResponse::new(fields);
+        if outgoing.set_status_code(response.status).is_err() {
+            _ = outgoing.set_status_code(500);
+        }
+        let outgoing_body = outgoing.body().expect("response body should be available");
+        ResponseOutparam::set(response_out, Ok(outgoing));
+
+        if let Some(body) = response.body {
+            let stream = outgoing_body
+                .write()
+                .expect("response body stream should be available");
+            for chunk in body.chunks(4096) {
+                if stream.blocking_write_and_flush(chunk).is_err() {
+                    // The client went away, so there is nobody left to send the rest of the body to
+                    return;
+                }
+            }
+        }
+        _ = OutgoingBody::finish(outgoing_body, None);
+    }
+}
+
+/// The Spin 1 equivalent of `request`, or `None` if its method has none
+fn to_spin_request(request: IncomingRequest) -> Option<Request> {
+    let method = match request.method() {
+        http_types::Method::Get => Method::Get,
+        http_types::Method::Post => Method::Post,
+        http_types::Method::Put => Method::Put,
+        http_types::Method::Delete => Method::Delete,
+        http_types::Method::Patch => Method::Patch,
+        http_types::Method::Head => Method::Head,
+        http_types::Method::Options => Method::Options,
+        http_types::Method::Connect
+        | http_types::Method::Trace
+        | http_types::Method::Other(_) => return None,
+    };
+    let uri = request.path_with_query().unwrap_or_else(|| "/".into());
+    let headers = request
+        .headers()
+        .entries()
+        .into_iter()
+        .map(|(name, value)| (name, String::from_utf8_lossy(&value).into_owned()))
+        .collect();
+
+    let body = request
+        .consume()
+        .ok()
+        .and_then(|body| read_all(&body.stream().ok()?))
+        .filter(|body| !body.is_empty());
+
+    Some(Request {
+        method,
+        uri,
+        headers,
+        params: Vec::new(),
+        body,
+    })
+}
+
+fn read_all(stream: &InputStream) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match stream.blocking_read(4096) {
+            Ok(chunk) => bytes.extend(chunk),
+            Err(StreamError::Closed) => break Some(bytes),
+            Err(StreamError::LastOperationFailed(_)) => break None,
+        }
+    }
+}
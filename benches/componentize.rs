@@ -0,0 +1,164 @@
+//! Benchmarks for componentizing large modules, such as those produced by interpreters bundled with their standard
+//! library, and for componentizing small modules repeatedly, as hosts which componentize on every cold start do
+//!
+//! Run with `cargo bench`; the modules are generated on the fly, so no fixtures need to be built first.
+
+use {
+    criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
+    spin_componentize::{Abi, ComponentizeOptions, Componentizer},
+    std::io::{self, Cursor},
+    wasm_encoder::{
+        CodeSection, ConstExpr, DataSection, EntityType, ExportKind, ExportSection, Function,
+        FunctionSection, ImportSection, Instruction, MemorySection, MemoryType, Module,
+        TypeSection, ValType,
+    },
+};
+
+const MEGABYTE: usize = 1024 * 1024;
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// A module importing a WASI function, with `size` bytes of data standing in for a bundled standard library
+///
+/// With `spin_allocator`, the module also exports the allocator which the Spin adapter expects of wit-bindgen 0.2
+/// modules.
+fn module(size: usize, spin_allocator: bool) -> Vec<u8> {
+    let mut types = TypeSection::new();
+    types.function([ValType::I32; 4], [ValType::I32]);
+    types.function([], []);
+    types.function([ValType::I32; 3], []);
+
+    let mut imports = ImportSection::new();
+    imports.import(
+        "wasi_snapshot_preview1",
+        "fd_write",
+        EntityType::Function(0),
+    );
+
+    let mut functions = FunctionSection::new();
+    functions.function(1);
+
+    let mut memories = MemorySection::new();
+    memories.memory(MemoryType {
+        minimum: size.div_ceil(PAGE_SIZE) as u64,
+        maximum: None,
+        memory64: false,
+        shared: false,
+    });
+
+    let mut exports = ExportSection::new();
+    exports.export("memory", ExportKind::Memory, 0);
+    exports.export("_initialize", ExportKind::Func, 1);
+
+    let mut code = CodeSection::new();
+    let mut initialize = Function::new([]);
+    initialize.instruction(&Instruction::End);
+    code.function(&initialize);
+
+    if spin_allocator {
+        functions.function(0).function(2);
+        exports.export("canonical_abi_realloc", ExportKind::Func, 2);
+        exports.export("canonical_abi_free", ExportKind::Func, 3);
+
+        let mut realloc = Function::new([]);
+        realloc
+            .instruction(&Instruction::I32Const(0))
+            .instruction(&Instruction::End);
+        code.function(&realloc);
+        let mut free = Function::new([]);
+        free.instruction(&Instruction::End);
+        code.function(&free);
+    }
+
+    let mut data = DataSection::new();
+    data.active(
+        0,
+        &ConstExpr::i32_const(0),
+        (0..size).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8),
+    );
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&memories)
+        .section(&exports)
+        .section(&code)
+        .section(&data);
+    module.finish()
+}
+
+fn componentize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("componentize");
+    group.sample_size(10);
+
+    for size in [50 * MEGABYTE, 100 * MEGABYTE] {
+        let module = module(size, true);
+        let id = format!("{}MB", size / MEGABYTE);
+        group.throughput(Throughput::Bytes(module.len() as u64));
+
+        // wit-bindgen 0.2 modules also have their imports moved to the Spin adapter before being encoded
+        for (abi, name) in [
+            (Abi::NewBindgen, "new-bindgen"),
+            (Abi::OldBindgen, "old-bindgen"),
+        ] {
+            let options = ComponentizeOptions::new().abi(abi);
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("slice-{name}"), &id),
+                &module,
+                |b, module| b.iter(|| options.componentize(module).unwrap()),
+            );
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("stream-{name}"), &id),
+                &module,
+                |b, module| {
+                    b.iter(|| {
+                        options
+                            .componentize_stream(Cursor::new(module), io::sink())
+                            .unwrap()
+                    })
+                },
+            );
+        }
+
+        let options = ComponentizeOptions::new().abi(Abi::NewBindgen);
+        let component = options.componentize(&module).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("stream-passthrough", &id),
+            &component,
+            |b, component| {
+                b.iter(|| {
+                    options
+                        .componentize_stream(Cursor::new(component), io::sink())
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn componentizer(c: &mut Criterion) {
+    // wit-bindgen 0.2 modules are adapted by the Spin adapter, whose world has to be decoded, restricted to the
+    // module's exports and embedded in the adapter again for every call unless a `Componentizer` is reused
+    let options = ComponentizeOptions::new().abi(Abi::OldBindgen);
+    let componentizer = Componentizer::new(options.clone());
+    let module = module(PAGE_SIZE, true);
+    let mut group = c.benchmark_group("cold-start");
+
+    group.bench_function("componentize", |b| {
+        b.iter(|| options.componentize(&module).unwrap())
+    });
+
+    group.bench_function("componentizer", |b| {
+        b.iter(|| componentizer.componentize(&module).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, componentize, componentizer);
+criterion_main!(benches);
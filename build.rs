@@ -4,6 +4,11 @@ use std::{
 };
 
 fn main() {
+    // Adapters must then be supplied through `ComponentizeOptions::adapter`
+    if env::var_os("CARGO_FEATURE_EMBEDDED_ADAPTERS").is_none() {
+        return;
+    }
+
     let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
     let adapters_dir = Path::new("adapters");
     std::fs::create_dir_all(out_dir.join("wasm32-unknown-unknown/release")).unwrap();
@@ -0,0 +1,22 @@
+#![no_main]
+
+use {
+    libfuzzer_sys::fuzz_target,
+    spin_componentize::{Abi, ComponentizeOptions},
+};
+
+// The first byte selects how the rest is adapted, so that every ABI is exercised and not just the one detected.
+// Malformed input should produce an error, never a panic.
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, module)) = data.split_first() else {
+        return;
+    };
+    let options = match selector % 5 {
+        0 => ComponentizeOptions::new(),
+        1 => ComponentizeOptions::new().abi(Abi::OldBindgen),
+        2 => ComponentizeOptions::new().abi(Abi::V0_4Bindgen),
+        3 => ComponentizeOptions::new().abi(Abi::NewBindgen),
+        _ => ComponentizeOptions::new().abi(Abi::Command),
+    };
+    _ = options.componentize(module);
+});
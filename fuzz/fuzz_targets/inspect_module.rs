@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Malformed input should produce an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    _ = spin_componentize::inspect_module(data);
+});
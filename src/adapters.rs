@@ -0,0 +1,329 @@
+use {
+    crate::{
+        check_sections, Adapter, ComponentizeError, ExportRegistry, Result, CUSTOM_SECTION_NAME,
+    },
+    semver::Version,
+    serde::Serialize,
+    std::{
+        collections::{BTreeSet, HashMap},
+        fmt,
+        sync::{Arc, Mutex},
+    },
+    wasmparser::{ExternalKind, Parser, Payload},
+    wit_component::metadata,
+    wit_parser::{Resolve, WorldId, WorldKey},
+};
+
+#[cfg(feature = "embedded-adapters")]
+const SPIN_ADAPTER: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_spin.wasm"
+));
+#[cfg(feature = "embedded-adapters")]
+const PREVIEW1_ADAPTER: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_upstream.wasm"
+));
+
+#[cfg(feature = "embedded-adapters")]
+const COMMAND_ADAPTER: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_command.wasm"
+));
+
+/// A WASI version which the adapters used to build a component may be expected to import
+///
+/// Componentization does not translate between versions: a component imports whichever version its adapters do.
+/// Expecting a version only checks the adapters against it, so that a component is never built for the wrong host.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WasiVersion {
+    /// WASI 0.2.0, imported by the embedded adapters
+    #[default]
+    V0_2_0,
+    /// The 0.2.0-rc-2023-11-10 release candidate, as understood by wasmtime 15 and 16
+    Rc2023_11_10,
+    /// The 0.2.0-rc-2023-10-18 release candidate, as understood by wasmtime 14
+    Rc2023_10_18,
+}
+
+impl WasiVersion {
+    /// The version suffix of the `wasi:*` interfaces of this version
+    pub fn version(&self) -> &'static str {
+        match self {
+            Self::V0_2_0 => "0.2.0",
+            Self::Rc2023_11_10 => "0.2.0-rc-2023-11-10",
+            Self::Rc2023_10_18 => "0.2.0-rc-2023-10-18",
+        }
+    }
+}
+
+impl fmt::Display for WasiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.version())
+    }
+}
+
+/// Every function in `wasi_snapshot_preview1`, all of which an adapter must export
+pub(crate) static PREVIEW1_FUNCTIONS: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "environ_get",
+    "environ_sizes_get",
+    "clock_res_get",
+    "clock_time_get",
+    "fd_advise",
+    "fd_allocate",
+    "fd_close",
+    "fd_datasync",
+    "fd_fdstat_get",
+    "fd_fdstat_set_flags",
+    "fd_fdstat_set_rights",
+    "fd_filestat_get",
+    "fd_filestat_set_size",
+    "fd_filestat_set_times",
+    "fd_pread",
+    "fd_prestat_get",
+    "fd_prestat_dir_name",
+    "fd_pwrite",
+    "fd_read",
+    "fd_readdir",
+    "fd_renumber",
+    "fd_seek",
+    "fd_sync",
+    "fd_tell",
+    "fd_write",
+    "path_create_directory",
+    "path_filestat_get",
+    "path_filestat_set_times",
+    "path_link",
+    "path_open",
+    "path_readlink",
+    "path_remove_directory",
+    "path_rename",
+    "path_symlink",
+    "path_unlink_file",
+    "poll_oneoff",
+    "proc_exit",
+    "proc_raise",
+    "sched_yield",
+    "random_get",
+    "sock_accept",
+    "sock_recv",
+    "sock_send",
+    "sock_shutdown",
+];
+
+/// The adapter compiled into this crate, if the `embedded-adapters` feature is enabled
+pub fn embedded_adapter(adapter: Adapter) -> Option<&'static [u8]> {
+    #[cfg(feature = "embedded-adapters")]
+    {
+        Some(match adapter {
+            Adapter::Spin => SPIN_ADAPTER,
+            Adapter::Preview1 => PREVIEW1_ADAPTER,
+            Adapter::Command => COMMAND_ADAPTER,
+        })
+    }
+
+    #[cfg(not(feature = "embedded-adapters"))]
+    {
+        _ = adapter;
+        None
+    }
+}
+
+/// The embedded adapter of the given kind if it imports the `expected` WASI version, or an error if no such adapter is
+/// available
+pub(crate) fn required_adapter(adapter: Adapter, expected: WasiVersion) -> Result<&'static [u8]> {
+    if expected != WasiVersion::default() {
+        return Err(ComponentizeError::MissingAdapter(adapter));
+    }
+    embedded_adapter(adapter).ok_or(ComponentizeError::MissingAdapter(adapter))
+}
+
+/// The Spin adapter with its component type metadata decoded, so that it can be shared between componentizations
+///
+/// The worlds prepared from it are kept for reuse, which assumes it is only shared between componentizations with
+/// the same [`ExportRegistry`].
+#[derive(Debug)]
+pub(crate) struct DecodedSpinAdapter {
+    /// The adapter without its metadata
+    adapter: Vec<u8>,
+    pub(crate) resolve: Resolve,
+    prepared: Mutex<HashMap<PreparedKey, Arc<PreparedWorld>>>,
+}
+
+/// A world, whether the adapter is embedded, and the exports allowed, which together identify a [`PreparedWorld`]
+type PreparedKey = (WorldId, bool, BTreeSet<String>);
+
+/// A world of the Spin adapter with its exports restricted to those of a module
+#[derive(Debug)]
+pub(crate) struct PreparedWorld {
+    /// The world encoded as `component-type` metadata
+    pub(crate) metadata: Vec<u8>,
+    /// The Spin adapter with the metadata embedded, if it was asked for
+    pub(crate) adapter: Option<Vec<u8>>,
+    pub(crate) kept_exports: Vec<String>,
+    pub(crate) dropped_exports: Vec<String>,
+}
+
+impl DecodedSpinAdapter {
+    pub(crate) fn decode(spin_adapter: &[u8]) -> Result<Self> {
+        let (adapter, bindgen) =
+            metadata::decode(spin_adapter).map_err(ComponentizeError::Metadata)?;
+        Ok(Self {
+            adapter,
+            resolve: bindgen.resolve,
+            prepared: Mutex::default(),
+        })
+    }
+
+    /// `world` with the interfaces registered in `registry` added to its exports and then any not in `allowed`
+    /// removed, along with the adapter embedding it if `embed` is set
+    ///
+    /// Each combination of world and exports is only prepared once.
+    pub(crate) fn prepare(
+        &self,
+        world: WorldId,
+        registry: &ExportRegistry,
+        embed: bool,
+        allowed: &BTreeSet<&str>,
+    ) -> Result<Arc<PreparedWorld>> {
+        let key = (
+            world,
+            embed,
+            allowed
+                .iter()
+                .map(|&interface| interface.to_owned())
+                .collect(),
+        );
+        let mut cache = self.prepared.lock().unwrap();
+        if let Some(prepared) = cache.get(&key) {
+            return Ok(prepared.clone());
+        }
+
+        let mut resolve = self.resolve.clone();
+        registry.add_exports(&mut resolve, world, allowed)?;
+        let (kept_exports, dropped_exports) = retain_exports(&mut resolve, world, allowed);
+
+        let metadata = metadata::encode(&resolve, world, wit_component::StringEncoding::UTF8, None)
+            .map_err(ComponentizeError::Metadata)?;
+        let adapter = if embed {
+            Some(crate::add_custom_section(
+                CUSTOM_SECTION_NAME,
+                &metadata,
+                &self.adapter,
+            )?)
+        } else {
+            None
+        };
+
+        let prepared = Arc::new(PreparedWorld {
+            metadata,
+            adapter,
+            kept_exports,
+            dropped_exports,
+        });
+        cache.insert(key, prepared.clone());
+        Ok(prepared)
+    }
+
+    /// The world of the given name
+    pub(crate) fn world(&self, name: &str) -> Result<WorldId> {
+        self.resolve
+            .worlds
+            .iter()
+            .find_map(|(k, v)| (v.name == name).then_some(k))
+            .ok_or_else(|| ComponentizeError::WorldNotFound(name.to_owned()))
+    }
+}
+
+/// Remove the interfaces not named in `allowed` from the exports of `world`, returning the names of the kept and
+/// dropped interfaces
+fn retain_exports(
+    resolve: &mut Resolve,
+    world: WorldId,
+    allowed: &BTreeSet<&str>,
+) -> (Vec<String>, Vec<String>) {
+    let mut kept_exports = Vec::new();
+    let mut dropped_exports = Vec::new();
+    resolve.worlds[world].exports.retain(|k, _| {
+        let k = match &k {
+            WorldKey::Name(n) => n,
+            WorldKey::Interface(i) => match &resolve.interfaces[*i].name {
+                Some(n) => n,
+                None => return true,
+            },
+        };
+        if allowed.contains(k.as_str()) {
+            kept_exports.push(k.clone());
+            true
+        } else {
+            dropped_exports.push(k.clone());
+            false
+        }
+    });
+
+    (kept_exports, dropped_exports)
+}
+
+/// Check that `bytes` is a core module exporting every `wasi_snapshot_preview1` function, as required of an
+/// adapter
+pub fn validate_adapter(adapter: Adapter, bytes: &[u8]) -> Result<()> {
+    check_sections(bytes)?;
+
+    let mut missing = PREVIEW1_FUNCTIONS.to_vec();
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::ExportSection(reader) = payload? {
+            for export in reader {
+                let export = export?;
+                if export.kind == ExternalKind::Func {
+                    missing.retain(|name| *name != export.name);
+                }
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ComponentizeError::InvalidAdapter {
+            adapter,
+            missing_exports: missing.into_iter().map(String::from).collect(),
+        })
+    }
+}
+
+/// Check that every `wasi` package imported by the world embedded in the adapter in `bytes` has the `expected`
+/// version
+pub(crate) fn check_wasi_version(
+    adapter: Adapter,
+    bytes: &[u8],
+    expected: WasiVersion,
+) -> Result<()> {
+    let (_, bindgen) = metadata::decode(bytes).map_err(ComponentizeError::Metadata)?;
+    let resolve = &bindgen.resolve;
+    let version = Version::parse(expected.version()).expect("WASI versions should be valid semver");
+
+    for key in resolve.worlds[bindgen.world].imports.keys() {
+        let WorldKey::Interface(id) = key else {
+            continue;
+        };
+        let Some(package) = resolve.interfaces[*id].package else {
+            continue;
+        };
+        let name = &resolve.packages[package].name;
+        if name.namespace == "wasi" && name.version.as_ref() != Some(&version) {
+            return Err(ComponentizeError::WasiVersionMismatch {
+                adapter,
+                expected,
+                found: name
+                    .version
+                    .as_ref()
+                    .map_or_else(|| "(unversioned)".to_owned(), Version::to_string),
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+#[derive(Parser)]
+#[clap(author, version, about)]
+pub struct Options {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Convert a Spin module to a component
+    Componentize {
+        #[clap(flatten)]
+        input: Input,
+
+        /// Name of file to write the component to (or stdout if not specified)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// How to treat the input
+        #[clap(short, long, value_enum, default_value_t = Mode::IfNecessary)]
+        mode: Mode,
+    },
+
+    /// Print the wit-bindgen version the module was built with
+    BindgenVersion {
+        #[clap(flatten)]
+        input: Input,
+    },
+
+    /// Print the producers, bindgen ABI and Spin interfaces of the module as JSON
+    Inspect {
+        #[clap(flatten)]
+        input: Input,
+    },
+
+    /// Componentize the module without writing the result anywhere
+    DryRun {
+        #[clap(flatten)]
+        input: Input,
+
+        /// How to treat the input
+        #[clap(short, long, value_enum, default_value_t = Mode::IfNecessary)]
+        mode: Mode,
+    },
+}
+
+#[derive(Args)]
+pub struct Input {
+    /// Name of Wasm module file to read (or stdin if not specified)
+    #[clap(short, long)]
+    pub input: Option<PathBuf>,
+}
+
+impl Input {
+    fn read(&self, stdin: &mut dyn Read) -> Result<Vec<u8>> {
+        Ok(if let Some(input) = &self.input {
+            fs::read(input)?
+        } else {
+            let mut buffer = Vec::new();
+            stdin.read_to_end(&mut buffer)?;
+            buffer
+        })
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum Mode {
+    /// Pass components through unchanged and componentize modules
+    IfNecessary,
+    /// Componentize a Spin module, failing if the input is already a component
+    Module,
+    /// Componentize a `wasi:cli/run` command module
+    Command,
+}
+
+impl Mode {
+    fn componentize<'a>(&self, input: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        Ok(match self {
+            Self::IfNecessary => spin_componentize::componentize_if_necessary(input)?,
+            Self::Module => Cow::Owned(spin_componentize::componentize(input)?),
+            Self::Command => Cow::Owned(spin_componentize::componentize_command(input)?),
+        })
+    }
+}
+
+fn main() -> Result<()> {
+    run(
+        Options::parse(),
+        &mut io::stdin().lock(),
+        &mut io::stdout().lock(),
+    )
+}
+
+/// Run the command given by `options`, with `stdin` and `stdout` standing in for the standard streams
+fn run(options: Options, stdin: &mut dyn Read, stdout: &mut dyn Write) -> Result<()> {
+    match options.command {
+        Command::Componentize {
+            input,
+            output,
+            mode,
+        } => {
+            let input = input.read(stdin)?;
+            let component = mode.componentize(&input)?;
+
+            let mut writer = if let Some(output) = &output {
+                Box::new(File::create(output)?) as Box<dyn Write>
+            } else {
+                Box::new(&mut *stdout)
+            };
+
+            writer.write_all(&component)?;
+            writer.flush()?;
+        }
+
+        Command::BindgenVersion { input } => {
+            let input = input.read(stdin)?;
+            writeln!(
+                stdout,
+                "{}",
+                spin_componentize::WitBindgenVersion::from_module(&input)?
+            )?;
+        }
+
+        Command::Inspect { input } => {
+            let input = input.read(stdin)?;
+            serde_json::to_writer_pretty(
+                &mut *stdout,
+                &spin_componentize::inspect_module(&input)?,
+            )?;
+            writeln!(stdout)?;
+        }
+
+        Command::DryRun { input, mode } => {
+            let input = input.read(stdin)?;
+            let component = mode.componentize(&input)?;
+            let verb = if matches!(component, Cow::Borrowed(_)) {
+                "would pass through"
+            } else {
+                "would componentize"
+            };
+            eprintln!(
+                "{verb} {} byte input as {} byte component",
+                input.len(),
+                component.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::iter};
+
+    fn run_with_stdin(args: &[&str], stdin: &[u8]) -> Result<Vec<u8>> {
+        let options =
+            Options::try_parse_from(iter::once("spin-componentize").chain(args.iter().copied()))?;
+        let mut stdout = Vec::new();
+        run(options, &mut &*stdin, &mut stdout)?;
+        Ok(stdout)
+    }
+
+    #[test]
+    fn componentize_command() -> Result<()> {
+        let module = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "_start"))
+            )"#,
+        )?;
+
+        let component = run_with_stdin(&["componentize", "--mode", "command"], &module)?;
+        assert!(wasmparser::Parser::is_component(&component));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bindgen_version() -> Result<()> {
+        // Without any component type metadata, a module can only have been built with wit-bindgen 0.2
+        let module = wat::parse_str("(module)")?;
+        assert_eq!(
+            b"0.2\n",
+            run_with_stdin(&["bindgen-version"], &module)?.as_slice()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stdin_to_stdout() -> Result<()> {
+        let component = wat::parse_str("(component)")?;
+        assert_eq!(component, run_with_stdin(&["componentize"], &component)?);
+
+        Ok(())
+    }
+}
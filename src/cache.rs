@@ -0,0 +1,184 @@
+use {
+    crate::{ComponentizeError, ComponentizeOptions, Result},
+    sha2::{Digest, Sha256},
+    std::{
+        borrow::Cow,
+        fmt::Write as _,
+        fs::{self, File},
+        io::{self, Write},
+        path::{Path, PathBuf},
+        process,
+        sync::atomic::{AtomicU64, Ordering},
+        time::SystemTime,
+    },
+    wasmparser::{Encoding, Parser, Payload},
+};
+
+/// Extension of the files holding cached components
+static EXTENSION: &str = "component";
+
+/// Length of the SHA-256 digest of the component stored at the start of each cache entry
+const DIGEST_LEN: usize = 32;
+
+/// Distinguishes the temporary files written by different threads of the same process
+static NEXT_TEMPORARY: AtomicU64 = AtomicU64::new(0);
+
+/// A directory of componentized modules, keyed by a hash of the module, the adapters, the options and the version
+/// of this crate
+///
+/// Entries are written to a temporary file and then renamed into place, so several processes may share a
+/// directory: a reader sees either a complete entry or none at all.  Each entry carries a digest of its contents,
+/// and entries which fail to match it are discarded and recomputed.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl Cache {
+    /// Use (and create, if necessary) the given directory
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_size: None,
+        })
+    }
+
+    /// Evict the least recently used entries whenever the cache grows beyond `max_size` bytes
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Like [`crate::componentize_if_necessary`], but reusing a previously cached component if there is one
+    pub fn componentize_if_necessary<'a>(
+        &self,
+        options: &ComponentizeOptions,
+        module_or_component: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>> {
+        match Parser::new(0).parse_all(module_or_component).next() {
+            Some(Ok(Payload::Version {
+                encoding: Encoding::Component,
+                ..
+            })) => return Ok(Cow::Borrowed(module_or_component)),
+            Some(Ok(Payload::Version { .. })) => (),
+            Some(Err(e)) => return Err(e.into()),
+            _ => return Err(ComponentizeError::UnknownEncoding),
+        }
+
+        let path = self.path(&key(options, module_or_component));
+        if let Some(component) = read(&path) {
+            return Ok(Cow::Owned(component));
+        }
+
+        let component = options.componentize(module_or_component)?;
+        self.write(&path, &component)?;
+        self.evict(&path)?;
+        Ok(Cow::Owned(component))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{EXTENSION}"))
+    }
+
+    fn write(&self, path: &Path, component: &[u8]) -> Result<()> {
+        let temporary = self.dir.join(format!(
+            ".{}-{}.tmp",
+            process::id(),
+            NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = (|| {
+            let mut file = File::create(&temporary)?;
+            file.write_all(&Sha256::digest(component))?;
+            file.write_all(component)?;
+            file.sync_all()?;
+            fs::rename(&temporary, path)
+        })();
+
+        if result.is_err() {
+            _ = fs::remove_file(&temporary);
+        }
+        result.map_err(Into::into)
+    }
+
+    /// Remove the least recently used entries other than `keep` until the cache fits within its maximum size
+    ///
+    /// `keep` is the entry just written, which would otherwise be a candidate whenever the filesystem's timestamps
+    /// are too coarse to tell it apart from older entries.
+    fn evict(&self, keep: &Path) -> Result<()> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) || path == keep {
+                continue;
+            }
+            // Another process may have evicted the entry in the meantime
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((used, metadata.len(), path));
+        }
+
+        let mut size = entries.iter().map(|(_, len, _)| len).sum::<u64>()
+            + fs::metadata(keep).map_or(0, |metadata| metadata.len());
+        entries.sort();
+        for (_, len, path) in entries {
+            if size <= max_size {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => size -= len,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => size -= len,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash everything which determines the component produced from `module`
+fn key(options: &ComponentizeOptions, module: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update([0]);
+    options.hash(&mut |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    });
+    hasher.update(module);
+
+    hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Read a cache entry, discarding it if it does not match its digest
+fn read(path: &Path) -> Option<Vec<u8>> {
+    let mut entry = fs::read(path).ok()?;
+    if entry.len() >= DIGEST_LEN && Sha256::digest(&entry[DIGEST_LEN..])[..] == entry[..DIGEST_LEN]
+    {
+        // Record the use for eviction; failing to do so only makes the entry more likely to be evicted
+        if let Ok(file) = File::options().append(true).open(path) {
+            _ = file.set_modified(SystemTime::now());
+        }
+        entry.drain(..DIGEST_LEN);
+        Some(entry)
+    } else {
+        _ = fs::remove_file(path);
+        None
+    }
+}
@@ -0,0 +1,110 @@
+use {
+    crate::{adapters::DecodedSpinAdapter, ComponentizeOptions, ComponentizeReport, Result},
+    std::{
+        num::NonZeroUsize,
+        panic,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// The outcome of componentizing one module of a batch
+#[derive(Debug)]
+pub struct BatchResult {
+    pub result: Result<(Vec<u8>, ComponentizeReport)>,
+    /// How long componentizing the module took, not counting the time it spent waiting for a thread
+    pub elapsed: Duration,
+}
+
+/// State shared between componentizations with the same options
+#[derive(Debug, Default)]
+pub(crate) struct Shared {
+    /// The Spin adapter, decoded for the first module which needs it
+    pub(crate) spin: Mutex<Option<Arc<DecodedSpinAdapter>>>,
+}
+
+/// Componentizes any number of modules with the same options, preparing the adapters only once
+///
+/// The Spin adapter is decoded for the first module which needs it, and its world is encoded and embedded in the
+/// adapter once for each distinct set of interfaces exported by the modules.  That leaves little besides the module
+/// itself to process for each call, which suits hosts which componentize modules on every cold start.
+#[derive(Debug)]
+pub struct Componentizer {
+    options: ComponentizeOptions,
+    shared: Shared,
+}
+
+impl Componentizer {
+    pub fn new(options: ComponentizeOptions) -> Self {
+        Self {
+            options,
+            shared: Shared::default(),
+        }
+    }
+
+    pub fn options(&self) -> &ComponentizeOptions {
+        &self.options
+    }
+
+    pub fn componentize(&self, module: &[u8]) -> Result<Vec<u8>> {
+        self.componentize_with_report(module)
+            .map(|(component, _)| component)
+    }
+
+    /// Like [`Componentizer::componentize`], but also returns a [`ComponentizeReport`] describing how the module was
+    /// converted
+    pub fn componentize_with_report(&self, module: &[u8]) -> Result<(Vec<u8>, ComponentizeReport)> {
+        self.options.componentize_shared(module, &self.shared)
+    }
+
+    /// Componentize each of `modules` on a pool of `threads` threads (one per available CPU by default), returning
+    /// the results in the same order as the modules
+    pub fn componentize_batch<M: AsRef<[u8]> + Sync>(
+        &self,
+        modules: &[M],
+        threads: Option<NonZeroUsize>,
+    ) -> Vec<BatchResult> {
+        let threads = threads
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+            .min(modules.len());
+        let next = AtomicUsize::new(0);
+
+        let mut results = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(module) = modules.get(index) else {
+                                break results;
+                            };
+                            let start = Instant::now();
+                            let result = self.componentize_with_report(module.as_ref());
+                            results.push((
+                                index,
+                                BatchResult {
+                                    result,
+                                    elapsed: start.elapsed(),
+                                },
+                            ));
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect::<Vec<_>>()
+        });
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
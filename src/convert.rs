@@ -6,15 +6,25 @@ use {
     wasmparser::{ExternalKind, TypeRef},
 };
 
+/// A type which has no wasm-encoder equivalent in the context it was found in
+#[derive(Debug)]
+pub struct UnsupportedType(pub String);
+
 struct IntoHeapType(wasmparser::HeapType);
 
-impl Into<HeapType> for IntoHeapType {
-    fn into(self) -> HeapType {
-        match self.0 {
+impl TryFrom<IntoHeapType> for HeapType {
+    type Error = UnsupportedType;
+
+    fn try_from(ty: IntoHeapType) -> Result<Self, Self::Error> {
+        Ok(match ty.0 {
             wasmparser::HeapType::Func => HeapType::Func,
             wasmparser::HeapType::Extern => HeapType::Extern,
-            wasmparser::HeapType::Concrete(_) => {
-                panic!("user-defined heap types not yet supported")
+            // Types outside the type section can only refer to types by their index in the module's type space;
+            // the rec groups and subtypes those indices refer to are copied over verbatim with the type section
+            wasmparser::HeapType::Concrete(index) => {
+                HeapType::Concrete(index.as_module_index().ok_or_else(|| {
+                    UnsupportedType(format!("heap type with non-module type index {index:?}"))
+                })?)
             }
             wasmparser::HeapType::Any => HeapType::Any,
             wasmparser::HeapType::None => HeapType::None,
@@ -25,54 +35,62 @@ impl Into<HeapType> for IntoHeapType {
             wasmparser::HeapType::Array => HeapType::Array,
             wasmparser::HeapType::I31 => HeapType::I31,
             wasmparser::HeapType::Exn => HeapType::Exn,
-        }
+        })
     }
 }
 
 struct IntoRefType(wasmparser::RefType);
 
-impl Into<RefType> for IntoRefType {
-    fn into(self) -> RefType {
-        RefType {
-            nullable: self.0.is_nullable(),
-            heap_type: IntoHeapType(self.0.heap_type()).into(),
-        }
+impl TryFrom<IntoRefType> for RefType {
+    type Error = UnsupportedType;
+
+    fn try_from(ty: IntoRefType) -> Result<Self, Self::Error> {
+        Ok(RefType {
+            nullable: ty.0.is_nullable(),
+            heap_type: IntoHeapType(ty.0.heap_type()).try_into()?,
+        })
     }
 }
 
 struct IntoValType(wasmparser::ValType);
 
-impl Into<ValType> for IntoValType {
-    fn into(self) -> ValType {
-        match self.0 {
+impl TryFrom<IntoValType> for ValType {
+    type Error = UnsupportedType;
+
+    fn try_from(ty: IntoValType) -> Result<Self, Self::Error> {
+        Ok(match ty.0 {
             wasmparser::ValType::I32 => ValType::I32,
             wasmparser::ValType::I64 => ValType::I64,
             wasmparser::ValType::F32 => ValType::F32,
             wasmparser::ValType::F64 => ValType::F64,
             wasmparser::ValType::V128 => ValType::V128,
-            wasmparser::ValType::Ref(ty) => ValType::Ref(IntoRefType(ty).into()),
-        }
+            wasmparser::ValType::Ref(ty) => ValType::Ref(IntoRefType(ty).try_into()?),
+        })
     }
 }
 
 struct IntoTagKind(wasmparser::TagKind);
 
-impl Into<TagKind> for IntoTagKind {
-    fn into(self) -> TagKind {
-        match self.0 {
+impl TryFrom<IntoTagKind> for TagKind {
+    type Error = UnsupportedType;
+
+    fn try_from(kind: IntoTagKind) -> Result<Self, Self::Error> {
+        Ok(match kind.0 {
             wasmparser::TagKind::Exception => TagKind::Exception,
-        }
+        })
     }
 }
 
 pub struct IntoEntityType(pub TypeRef);
 
-impl Into<EntityType> for IntoEntityType {
-    fn into(self) -> EntityType {
-        match self.0 {
+impl TryFrom<IntoEntityType> for EntityType {
+    type Error = UnsupportedType;
+
+    fn try_from(ty: IntoEntityType) -> Result<Self, Self::Error> {
+        Ok(match ty.0 {
             TypeRef::Func(index) => EntityType::Function(index),
             TypeRef::Table(ty) => EntityType::Table(TableType {
-                element_type: IntoRefType(ty.element_type).into(),
+                element_type: IntoRefType(ty.element_type).try_into()?,
                 minimum: ty.initial,
                 maximum: ty.maximum,
             }),
@@ -83,27 +101,29 @@ impl Into<EntityType> for IntoEntityType {
                 shared: ty.shared,
             }),
             TypeRef::Global(ty) => EntityType::Global(GlobalType {
-                val_type: IntoValType(ty.content_type).into(),
+                val_type: IntoValType(ty.content_type).try_into()?,
                 mutable: ty.mutable,
             }),
             TypeRef::Tag(ty) => EntityType::Tag(TagType {
-                kind: IntoTagKind(ty.kind).into(),
+                kind: IntoTagKind(ty.kind).try_into()?,
                 func_type_idx: ty.func_type_idx,
             }),
-        }
+        })
     }
 }
 
 pub struct IntoExportKind(pub ExternalKind);
 
-impl Into<ExportKind> for IntoExportKind {
-    fn into(self) -> ExportKind {
-        match self.0 {
+impl TryFrom<IntoExportKind> for ExportKind {
+    type Error = UnsupportedType;
+
+    fn try_from(kind: IntoExportKind) -> Result<Self, Self::Error> {
+        Ok(match kind.0 {
             ExternalKind::Func => ExportKind::Func,
             ExternalKind::Table => ExportKind::Table,
             ExternalKind::Memory => ExportKind::Memory,
             ExternalKind::Global => ExportKind::Global,
             ExternalKind::Tag => ExportKind::Tag,
-        }
+        })
     }
 }
@@ -0,0 +1,110 @@
+use {
+    crate::{section_data, ComponentizeError, Result, ScannedModule},
+    serde::Serialize,
+    std::{borrow::Cow, ops::Range},
+    wasmparser::{Parser, Payload},
+};
+
+/// Custom sections removed by [`DebugInfo::Strip`], besides those whose names start with `.debug_`
+static STRIPPED_SECTIONS: &[&str] = &[
+    "name",
+    "producers",
+    "sourceMappingURL",
+    "external_debug_info",
+];
+
+/// What to do with the debug information and `producers` metadata of the module and its adapter
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DebugInfo {
+    /// Copy whatever custom sections the module and adapter contain into the component
+    #[default]
+    Keep,
+    /// Like `Keep`, but also check that the core module embedded in the component has the same code and the same
+    /// `name` and DWARF sections as the input, so that a debugger can map it back to source
+    Preserve,
+    /// Remove the `name`, DWARF, source map and `producers` sections from both the module and the adapter
+    Strip,
+}
+
+/// Remove the sections stripped by [`DebugInfo::Strip`] from `module`
+pub(crate) fn strip(module: &[u8]) -> Result<Cow<[u8]>> {
+    let stripped = ScannedModule::read(&[], module, is_stripped)?.module;
+    Ok(if stripped.len() < module.len() {
+        Cow::Owned(stripped)
+    } else {
+        Cow::Borrowed(module)
+    })
+}
+
+/// Whether [`DebugInfo::Strip`] removes the custom section named `name`
+pub(crate) fn is_stripped(name: &str) -> bool {
+    name.starts_with(".debug_") || STRIPPED_SECTIONS.contains(&name)
+}
+
+/// The code section and debug sections of a core module
+#[derive(PartialEq, Eq)]
+struct DebugSections<'a> {
+    code: Option<&'a [u8]>,
+    custom: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> DebugSections<'a> {
+    fn new(binary: &'a [u8], range: Range<usize>) -> Result<Self> {
+        let module = section_data(binary, range.clone())?;
+        let mut sections = Self {
+            code: None,
+            custom: Vec::new(),
+        };
+
+        for payload in Parser::new(range.start as u64).parse_all(module) {
+            match payload? {
+                Payload::CodeSectionStart { range, .. } => {
+                    sections.code = Some(section_data(binary, range)?);
+                }
+                Payload::CustomSection(section)
+                    if section.name() == "name" || section.name().starts_with(".debug_") =>
+                {
+                    sections.custom.push((section.name(), section.data()));
+                }
+                _ => (),
+            }
+        }
+        Ok(sections)
+    }
+}
+
+/// Check that one of the core modules at the top level of `component` has the same code, `name` section and DWARF
+/// sections as `module`
+pub(crate) fn verify(module: &[u8], component: &[u8]) -> Result<()> {
+    let expected = DebugSections::new(module, 0..module.len())?;
+
+    let mut depth = 0;
+    let mut same_code = false;
+    for payload in Parser::new(0).parse_all(component) {
+        match payload? {
+            Payload::ModuleSection { range, .. } => {
+                if depth == 0 {
+                    let embedded = DebugSections::new(component, range)?;
+                    if embedded == expected {
+                        return Ok(());
+                    }
+                    same_code |= embedded.code == expected.code;
+                }
+                depth += 1;
+            }
+            Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            _ => (),
+        }
+    }
+
+    Err(ComponentizeError::DebugInfo(
+        if same_code {
+            "the name or DWARF sections of the embedded module differ from the input"
+        } else {
+            "the code of the embedded module differs from the input"
+        }
+        .to_owned(),
+    ))
+}
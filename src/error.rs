@@ -0,0 +1,189 @@
+use {
+    crate::{Adapter, ExportDiagnostics, WasiVersion},
+    std::{error, fmt},
+};
+
+/// The error type returned by the componentization functions in this crate
+#[derive(Debug)]
+pub enum ComponentizeError {
+    /// Reading the input or writing the output failed
+    Io(std::io::Error),
+
+    /// The input could not be parsed as a WebAssembly binary
+    InvalidBinary(wasmparser::BinaryReaderError),
+
+    /// The input did not start with a WebAssembly module or component header
+    UnknownEncoding,
+
+    /// The module was produced by a wit-bindgen version which cannot be adapted
+    UnsupportedBindgenVersion(String),
+
+    /// The component type metadata embedded in the module or adapter could not be decoded or encoded
+    Metadata(anyhow::Error),
+
+    /// The module uses a construct which cannot be re-encoded
+    Unsupported {
+        /// Description of the construct
+        construct: String,
+        /// The section it was found in
+        section: &'static str,
+        /// The import (as `module::name`) or export it was found in
+        item: String,
+        /// Byte offset of the import or export in the module
+        offset: usize,
+    },
+
+    /// The component does not preserve the debug information of the module, as requested with
+    /// [`crate::DebugInfo::Preserve`]
+    DebugInfo(String),
+
+    /// The component passed to [`crate::verify`] differs from the one produced from the module
+    VerificationFailed {
+        /// Offset of the first byte which differs
+        offset: usize,
+        expected_size: usize,
+        actual_size: usize,
+    },
+
+    /// The adapter does not contain the world needed to adapt the module
+    WorldNotFound(String),
+
+    /// `wit_component::ComponentEncoder` failed to encode or validate the component
+    Encode(anyhow::Error),
+
+    /// No adapter of the required kind was supplied, and there is no embedded adapter which imports the expected WASI
+    /// version
+    MissingAdapter(Adapter),
+
+    /// A caller-supplied adapter does not export every `wasi_snapshot_preview1` function
+    InvalidAdapter {
+        adapter: Adapter,
+        missing_exports: Vec<String>,
+    },
+
+    /// A wit-bindgen 0.2 module has exports which do not map to any interface, and strict mode is enabled
+    UnrecognizedExports(ExportDiagnostics),
+
+    /// A caller-supplied adapter imports a different WASI version than the one expected with
+    /// [`crate::ComponentizeOptions::expect_wasi_version`]
+    WasiVersionMismatch {
+        adapter: Adapter,
+        expected: WasiVersion,
+        found: String,
+    },
+
+    /// An export registered with [`crate::ExportRegistry::register`] maps to an interface which the Spin adapter's
+    /// world does not export
+    UnknownInterface { export: String, interface: String },
+}
+
+impl ComponentizeError {
+    /// The full diagnostic produced by wit-component, if this error originated there
+    pub fn diagnostic(&self) -> Option<String> {
+        match self {
+            Self::Metadata(e) | Self::Encode(e) => Some(format!("{e:?}")),
+            Self::UnrecognizedExports(diagnostics) => Some(diagnostics.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ComponentizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("I/O error"),
+            Self::InvalidBinary(_) => f.write_str("unable to parse binary"),
+            Self::UnknownEncoding => f.write_str("unable to determine wasm binary encoding"),
+            Self::UnsupportedBindgenVersion(version) => write!(
+                f,
+                "cannot adapt modules created with wit-bindgen version {version}"
+            ),
+            Self::Metadata(_) => f.write_str("unable to process component type metadata"),
+            Self::Unsupported {
+                construct,
+                section,
+                item,
+                offset,
+            } => write!(
+                f,
+                "unsupported {construct} in {section} `{item}` at offset {offset:#x}"
+            ),
+            Self::DebugInfo(reason) => write!(f, "debug information not preserved: {reason}"),
+            Self::VerificationFailed {
+                offset,
+                expected_size,
+                actual_size,
+            } => write!(
+                f,
+                "component differs from the expected output at offset {offset:#x} \
+                 ({actual_size} bytes, expected {expected_size})"
+            ),
+            Self::WorldNotFound(world) => write!(f, "world not found: {world}"),
+            Self::Encode(_) => f.write_str("failed to encode component"),
+            Self::MissingAdapter(adapter) => write!(f, "no {adapter} adapter available"),
+            Self::InvalidAdapter {
+                adapter,
+                missing_exports,
+            } => write!(
+                f,
+                "{adapter} adapter is missing wasi_snapshot_preview1 exports: {}",
+                missing_exports.join(", ")
+            ),
+            Self::UnrecognizedExports(diagnostics) => {
+                f.write_str("unrecognized exports: ")?;
+                for (i, export) in diagnostics.unrecognized.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{export}")?;
+                }
+                Ok(())
+            }
+            Self::WasiVersionMismatch {
+                adapter,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{adapter} adapter imports WASI {found}, but WASI {expected} was expected"
+            ),
+            Self::UnknownInterface { export, interface } => write!(
+                f,
+                "export `{export}` is registered to interface `{interface}`, which the Spin world does not export"
+            ),
+        }
+    }
+}
+
+impl error::Error for ComponentizeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::InvalidBinary(e) => Some(e),
+            Self::Metadata(e) | Self::Encode(e) => Some(&**e),
+            Self::UnknownEncoding
+            | Self::UnsupportedBindgenVersion(_)
+            | Self::Unsupported { .. }
+            | Self::DebugInfo(_)
+            | Self::VerificationFailed { .. }
+            | Self::WorldNotFound(_)
+            | Self::MissingAdapter(_)
+            | Self::InvalidAdapter { .. }
+            | Self::UnrecognizedExports(_)
+            | Self::WasiVersionMismatch { .. }
+            | Self::UnknownInterface { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ComponentizeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<wasmparser::BinaryReaderError> for ComponentizeError {
+    fn from(e: wasmparser::BinaryReaderError) -> Self {
+        Self::InvalidBinary(e)
+    }
+}
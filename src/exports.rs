@@ -0,0 +1,250 @@
+use {
+    crate::{ComponentizeError, Result},
+    serde::Serialize,
+    std::{collections::BTreeSet, fmt, path::Path},
+    wit_parser::{Resolve, UnresolvedPackage, WorldId, WorldItem, WorldKey},
+};
+
+/// Additional mappings from the exports of wit-bindgen 0.2 modules to the interfaces exported by the componentized
+/// world
+///
+/// Modules built with wit-bindgen 0.2 export plain functions such as `handle-http-request`, which are only kept in
+/// the component if they map to an interface of the Spin world.  Registering a mapping here lets custom triggers
+/// (timers, MQTT, cron and so on) survive componentization.  Registered mappings take precedence over the built-in
+/// ones.
+///
+/// The Spin adapter is what implements these interfaces on behalf of the module, so mappings to interfaces not
+/// already implemented by the embedded adapter need a Spin adapter supplied with
+/// [`crate::ComponentizeOptions::adapter`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportRegistry {
+    mappings: Vec<ExportMapping>,
+}
+
+/// Prefixes of function exports which wit-bindgen and the toolchain add to every module, and which never map to an
+/// interface
+static SUPPORT_EXPORT_PREFIXES: &[&str] = &[
+    "canonical_abi_",
+    "cabi_",
+    "asyncify_",
+    "_initialize",
+    "_start",
+    "__",
+];
+
+#[derive(Debug, Clone)]
+struct ExportMapping {
+    export: String,
+    interface: String,
+    wit: Option<String>,
+}
+
+impl ExportRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map `export` to `interface`, which must already be exported by the Spin adapter's world
+    ///
+    /// Componentizing a wit-bindgen 0.2 module fails with [`ComponentizeError::UnknownInterface`] if it is not.
+    pub fn register(self, export: impl Into<String>, interface: impl Into<String>) -> Self {
+        self.insert(export.into(), interface.into(), None)
+    }
+
+    /// Map `export` to `interface`, which is defined by the WIT package in `wit`
+    ///
+    /// The package may refer to any package known to the Spin adapter's world, such as `wasi:http`.  It is added
+    /// to the world's exports when componentizing a module which exports `export`.
+    pub fn register_wit(
+        self,
+        export: impl Into<String>,
+        interface: impl Into<String>,
+        wit: impl Into<String>,
+    ) -> Self {
+        self.insert(export.into(), interface.into(), Some(wit.into()))
+    }
+
+    fn insert(mut self, export: String, interface: String, wit: Option<String>) -> Self {
+        self.mappings.retain(|m| m.export != export);
+        self.mappings.push(ExportMapping {
+            export,
+            interface,
+            wit,
+        });
+        self
+    }
+
+    /// The names of all registered exports
+    pub(crate) fn exports(&self) -> impl Iterator<Item = &str> {
+        self.mappings.iter().map(|m| m.export.as_str())
+    }
+
+    /// The interface `export` is mapped to, if it has been registered
+    pub fn interface(&self, export: &str) -> Option<&str> {
+        self.mappings
+            .iter()
+            .find_map(|m| (m.export == export).then_some(m.interface.as_str()))
+    }
+
+    /// Add the interfaces in `allowed` which are backed by registered WIT to the exports of `world`, after checking
+    /// that `world` already exports every other registered interface
+    pub(crate) fn add_exports(
+        &self,
+        resolve: &mut Resolve,
+        world: WorldId,
+        allowed: &BTreeSet<&str>,
+    ) -> Result<()> {
+        for mapping in &self.mappings {
+            let Some(wit) = &mapping.wit else {
+                if !exports_interface(resolve, world, &mapping.interface) {
+                    return Err(ComponentizeError::UnknownInterface {
+                        export: mapping.export.clone(),
+                        interface: mapping.interface.clone(),
+                    });
+                }
+                continue;
+            };
+            if !allowed.contains(mapping.interface.as_str()) {
+                continue;
+            }
+
+            let path = format!("{}.wit", mapping.export);
+            let package = UnresolvedPackage::parse(Path::new(&path), wit)
+                .and_then(|package| resolve.push(package))
+                .map_err(|e| {
+                    ComponentizeError::Metadata(
+                        e.context(format!("invalid WIT registered for `{}`", mapping.export)),
+                    )
+                })?;
+
+            let interface = *resolve.packages[package]
+                .interfaces
+                .get(&mapping.interface)
+                .ok_or_else(|| {
+                    ComponentizeError::Metadata(anyhow::anyhow!(
+                        "interface `{}` not found in WIT registered for `{}`",
+                        mapping.interface,
+                        mapping.export
+                    ))
+                })?;
+
+            resolve.worlds[world].exports.insert(
+                WorldKey::Interface(interface),
+                WorldItem::Interface(interface),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `world` exports an interface named `name`
+fn exports_interface(resolve: &Resolve, world: WorldId, name: &str) -> bool {
+    resolve.worlds[world].exports.keys().any(|key| match key {
+        WorldKey::Name(n) => n == name,
+        WorldKey::Interface(id) => resolve.interfaces[*id].name.as_deref() == Some(name),
+    })
+}
+
+/// The function exports of a wit-bindgen 0.2 module and how they were matched against the known exports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExportDiagnostics {
+    /// Every function export of the module, excluding those added by the toolchain
+    pub found: Vec<String>,
+    /// The exports which map to an interface
+    pub matched: Vec<String>,
+    /// The exports which do not map to any interface
+    pub unrecognized: Vec<UnrecognizedExport>,
+}
+
+/// An export of a wit-bindgen 0.2 module which does not map to any interface
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnrecognizedExport {
+    pub name: String,
+    /// The known export with the most similar name, if any is plausibly what was meant
+    pub closest: Option<String>,
+}
+
+impl fmt::Display for UnrecognizedExport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`", self.name)?;
+        if let Some(closest) = &self.closest {
+            write!(f, " (did you mean `{closest}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExportDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "exports found: {}", self.found.join(", "))?;
+        writeln!(f, "exports matched: {}", self.matched.join(", "))?;
+        write!(f, "exports not recognized:")?;
+        for export in &self.unrecognized {
+            write!(f, "\n  {export}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ExportDiagnostics {
+    /// Match the function exports of a module against the names of the known exports
+    pub(crate) fn new<'a>(exports: &[String], known: impl IntoIterator<Item = &'a str>) -> Self {
+        let known = known.into_iter().collect::<Vec<_>>();
+        let mut diagnostics = Self::default();
+
+        for export in exports {
+            if SUPPORT_EXPORT_PREFIXES
+                .iter()
+                .any(|prefix| export.starts_with(prefix))
+            {
+                continue;
+            }
+
+            diagnostics.found.push(export.clone());
+            if known.contains(&export.as_str()) {
+                diagnostics.matched.push(export.clone());
+            } else {
+                diagnostics.unrecognized.push(UnrecognizedExport {
+                    name: export.clone(),
+                    closest: closest_name(export, &known).map(str::to_owned),
+                });
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// The name in `candidates` with the smallest edit distance from `name`, provided it is no more than a third of
+/// the length of `name` away
+fn closest_name<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= (name.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a == *b {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
@@ -0,0 +1,189 @@
+use {
+    crate::{
+        Result, WitBindgenVersion, EXPORT_INTERFACES, V0_2_IMPORT_MODULES, V0_4_IMPORT_MODULES,
+    },
+    serde::Serialize,
+    wasmparser::{Parser, Payload, ProducersSectionReader},
+    wit_component::metadata,
+};
+
+/// Import module names used by Spin SDKs built with both wit-bindgen 0.2 and wit-bindgen 0.3/0.4
+static SHARED_LEGACY_IMPORT_MODULES: &[&str] = &["key-value", "llm", "sqlite"];
+
+/// Prefix of the interfaces imported and exported by modules targeting the `fermyon:spin` package
+static SPIN_PACKAGE_PREFIX: &str = "fermyon:spin/";
+
+/// Information about a module, gathered without componentizing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModuleInfo {
+    /// Contents of the module's `producers` metadata
+    pub producers: Producers,
+    /// The bindgen ABI family the module was built against
+    pub wit_bindgen_version: WitBindgenVersion,
+    /// The toolchain which most likely produced the module
+    pub toolchain: Toolchain,
+    /// Spin interfaces the module imports, in order of first appearance
+    pub spin_imports: Vec<String>,
+    /// Spin interfaces the module exports, in order of first appearance
+    pub spin_exports: Vec<String>,
+}
+
+/// The fields of a `producers` custom section
+///
+/// This merges the module's own `producers` section with any producers recorded in its `component-type` custom
+/// sections.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Producers {
+    pub language: Vec<Producer>,
+    pub sdk: Vec<Producer>,
+    pub processed_by: Vec<Producer>,
+}
+
+impl Producers {
+    fn add(&mut self, field: &str, name: &str, version: &str) {
+        let values = match field {
+            "language" => &mut self.language,
+            "sdk" => &mut self.sdk,
+            "processed-by" => &mut self.processed_by,
+            _ => return,
+        };
+
+        if !values
+            .iter()
+            .any(|p| p.name == name && p.version == version)
+        {
+            values.push(Producer {
+                name: name.to_owned(),
+                version: version.to_owned(),
+            });
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.language
+            .iter()
+            .chain(&self.sdk)
+            .chain(&self.processed_by)
+            .any(|p| p.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A single entry in a `producers` field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Producer {
+    pub name: String,
+    pub version: String,
+}
+
+/// Toolchains recognised by [`inspect_module`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Toolchain {
+    Rust,
+    TinyGo,
+    ComponentizePy,
+    ComponentizeJs,
+    /// Some other toolchain, identified by the language named in the `producers` section
+    Other(String),
+    Unknown,
+}
+
+/// Describe the producers, bindgen ABI and Spin interfaces of the given module
+pub fn inspect_module(module: &[u8]) -> Result<ModuleInfo> {
+    let wit_bindgen_version = WitBindgenVersion::from_module(module)?;
+
+    let mut producers = Producers::default();
+    let mut spin_imports = Vec::<String>::new();
+    let mut spin_exports = Vec::<String>::new();
+    let mut asyncify = false;
+
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::CustomSection(section) if section.name() == "producers" => {
+                for field in ProducersSectionReader::new(section.data(), section.data_offset())? {
+                    let field = field?;
+                    for value in field.values {
+                        let value = value?;
+                        producers.add(field.name, value.name, value.version);
+                    }
+                }
+            }
+
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if (V0_2_IMPORT_MODULES.contains(&import.module)
+                        || V0_4_IMPORT_MODULES.contains(&import.module)
+                        || SHARED_LEGACY_IMPORT_MODULES.contains(&import.module)
+                        || import.module.starts_with(SPIN_PACKAGE_PREFIX))
+                        && !spin_imports.iter().any(|i| i == import.module)
+                    {
+                        spin_imports.push(import.module.to_owned());
+                    }
+                }
+            }
+
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    asyncify |= export.name.starts_with("asyncify_");
+                    let interface = if EXPORT_INTERFACES.iter().any(|(k, _)| *k == export.name) {
+                        Some(export.name)
+                    } else {
+                        export.name.split_once('#').and_then(|(interface, _)| {
+                            (interface.starts_with(SPIN_PACKAGE_PREFIX)
+                                || EXPORT_INTERFACES.iter().any(|(_, v)| *v == interface))
+                            .then_some(interface)
+                        })
+                    };
+                    if let Some(interface) = interface {
+                        if !spin_exports.iter().any(|e| e == interface) {
+                            spin_exports.push(interface.to_owned());
+                        }
+                    }
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    // `WitBindgenVersion::from_module` has already reported any metadata we cannot do without
+    if let Some(bindgen_producers) = metadata::decode(module)
+        .ok()
+        .and_then(|(_, bindgen)| bindgen.producers)
+    {
+        for field in ["language", "sdk", "processed-by"] {
+            if let Some(values) = bindgen_producers.get(field) {
+                for (name, version) in values.iter() {
+                    producers.add(field, name, version);
+                }
+            }
+        }
+    }
+
+    let toolchain = if producers.contains("componentize-py") {
+        Toolchain::ComponentizePy
+    } else if producers.contains("componentize-js") {
+        Toolchain::ComponentizeJs
+    } else if producers.contains("tinygo") || producers.contains("go") {
+        Toolchain::TinyGo
+    } else if producers.contains("rust") || producers.contains("rustc") {
+        Toolchain::Rust
+    } else if let Some(language) = producers.language.first() {
+        Toolchain::Other(language.name.clone())
+    } else if asyncify {
+        // TinyGo does not emit a `producers` section, but its default WASI scheduler exports the asyncify API
+        Toolchain::TinyGo
+    } else {
+        Toolchain::Unknown
+    };
+
+    Ok(ModuleInfo {
+        producers,
+        wit_bindgen_version,
+        toolchain,
+        spin_imports,
+        spin_exports,
+    })
+}
@@ -1,33 +1,68 @@
 #![deny(warnings)]
 
 use {
-    anyhow::{anyhow, Context, Result},
+    adapters::{required_adapter, DecodedSpinAdapter},
     convert::{IntoEntityType, IntoExportKind},
-    std::{borrow::Cow, collections::HashSet},
+    scan::ScannedModule,
+    serde::Serialize,
+    std::{
+        borrow::Cow,
+        collections::{BTreeMap, BTreeSet},
+        fmt,
+        ops::Range,
+    },
     wasm_encoder::{CustomSection, ExportSection, ImportSection, Module, RawSection},
     wasmparser::{Encoding, Parser, Payload},
     wit_component::{metadata, ComponentEncoder},
 };
 
+mod adapters;
+#[cfg(feature = "cache")]
+mod cache;
+mod componentizer;
 mod convert;
+mod debug_info;
+mod error;
+mod exports;
+mod inspect;
+mod options;
+mod scan;
+mod spin_world;
 
-const SPIN_ADAPTER: &[u8] = include_bytes!(concat!(
-    env!("OUT_DIR"),
-    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_spin.wasm"
-));
-const PREVIEW1_ADAPTER: &[u8] = include_bytes!(concat!(
-    env!("OUT_DIR"),
-    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_upstream.wasm"
-));
+#[cfg(feature = "cache")]
+pub use cache::Cache;
 
-const COMMAND_ADAPTER: &[u8] = include_bytes!(concat!(
-    env!("OUT_DIR"),
-    "/wasm32-unknown-unknown/release/wasi_snapshot_preview1_command.wasm"
-));
+pub use {
+    adapters::{embedded_adapter, validate_adapter, WasiVersion},
+    componentizer::{BatchResult, Componentizer},
+    debug_info::DebugInfo,
+    error::ComponentizeError,
+    exports::{ExportDiagnostics, ExportRegistry, UnrecognizedExport},
+    inspect::{inspect_module, ModuleInfo, Producer, Producers, Toolchain},
+    options::{infer_abi, Abi, ComponentizeOptions},
+    spin_world::SpinWorld,
+};
+
+type Result<T, E = ComponentizeError> = std::result::Result<T, E>;
 
 static ADAPTER_NAME: &str = "wasi_snapshot_preview1";
 static CUSTOM_SECTION_NAME: &str = "component-type:reactor";
 static WORLD_NAME: &str = "reactor";
+static POST_RETURN_PREFIX: &str = "cabi_post_";
+
+static NEW_ABI_PRODUCERS: &[&str] = &["componentize-py", "componentize-js"];
+
+/// Import module names specific to Spin SDKs built with wit-bindgen 0.2
+static V0_2_IMPORT_MODULES: &[&str] = &[
+    "spin-config",
+    "wasi-outbound-http",
+    "outbound-redis",
+    "outbound-pg",
+    "outbound-mysql",
+];
+
+/// Import module names specific to Spin SDKs built with wit-bindgen 0.3 and 0.4
+static V0_4_IMPORT_MODULES: &[&str] = &["config", "http", "redis", "postgres", "mysql"];
 
 static EXPORT_INTERFACES: &[(&str, &str)] = &[
     ("handle-redis-message", "inbound-redis"),
@@ -36,7 +71,7 @@ static EXPORT_INTERFACES: &[(&str, &str)] = &[
 
 pub fn componentize_if_necessary(module_or_component: &[u8]) -> Result<Cow<[u8]>> {
     for payload in Parser::new(0).parse_all(module_or_component) {
-        match payload.context("unable to parse binary")? {
+        match payload? {
             Payload::Version { encoding, .. } => {
                 return match encoding {
                     Encoding::Component => Ok(Cow::Borrowed(module_or_component)),
@@ -46,175 +81,680 @@ pub fn componentize_if_necessary(module_or_component: &[u8]) -> Result<Cow<[u8]>
             _ => (),
         }
     }
-    Err(anyhow!("unable to determine wasm binary encoding"))
+    Err(ComponentizeError::UnknownEncoding)
+}
+
+/// Like [`componentize_if_necessary`], but reads the input from `input` and writes the result to `output`, copying
+/// components through without buffering them
+pub fn componentize_stream(
+    input: impl std::io::Read,
+    output: impl std::io::Write,
+) -> Result<Option<ComponentizeReport>> {
+    ComponentizeOptions::default().componentize_stream(input, output)
+}
+
+/// Componentize `module` again and check that the result is byte-for-byte identical to `component`
+///
+/// Componentization is deterministic, so this succeeds whenever `component` was produced from `module` by the same
+/// version of this crate with default options.
+pub fn verify(module: &[u8], component: &[u8]) -> Result<()> {
+    ComponentizeOptions::default().verify(module, component)
 }
 
 pub fn componentize(module: &[u8]) -> Result<Vec<u8>> {
-    match WitBindgenVersion::from_module(module)? {
-        WitBindgenVersion::V0_2 => componentize_old_bindgen(module),
-        WitBindgenVersion::GreaterThanV0_4 => componentize_new_bindgen(module),
-        WitBindgenVersion::Other(other) => Err(anyhow::anyhow!(
-            "cannot adapt modules created with wit-bindgen version {other}"
-        )),
+    componentize_with_report(module).map(|(component, _)| component)
+}
+
+/// Like [`componentize`], but also returns a [`ComponentizeReport`] describing how the module was converted
+pub fn componentize_with_report(module: &[u8]) -> Result<(Vec<u8>, ComponentizeReport)> {
+    ComponentizeOptions::default().componentize_with_report(module)
+}
+
+/// Details of how a module was converted to a component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentizeReport {
+    /// The wit-bindgen version detected from the module's `producers` metadata
+    ///
+    /// This is `None` if the ABI was specified with [`ComponentizeOptions::abi`] and the metadata could not be
+    /// read.
+    pub wit_bindgen_version: Option<WitBindgenVersion>,
+    /// The ABI the module was componentized as
+    pub abi: Abi,
+    /// The adapter embedded in the component
+    pub adapter: Adapter,
+    /// Imports which were renamed (wit-bindgen 0.4 and older modules only)
+    pub rewritten_imports: Vec<RewrittenImport>,
+    /// Interfaces kept in the Spin world's exports (wit-bindgen 0.4 and older modules only)
+    pub kept_exports: Vec<String>,
+    /// Interfaces dropped from the Spin world's exports because the module does not implement them
+    /// (wit-bindgen 0.4 and older modules only)
+    pub dropped_exports: Vec<String>,
+    /// Problems which did not prevent componentization, such as unrecognized exports being dropped
+    /// (wit-bindgen 0.2 modules only)
+    pub warnings: Vec<String>,
+    /// Size of the input module in bytes
+    pub input_size: usize,
+    /// Size of the resulting component in bytes
+    pub output_size: usize,
+}
+
+/// The adapter used to convert a module to a component
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Adapter {
+    /// The Spin-aware adapter used for wit-bindgen 0.2 modules
+    Spin,
+    /// The upstream WASI preview 1 reactor adapter
+    Preview1,
+    /// The upstream WASI preview 1 command adapter
+    Command,
+}
+
+impl fmt::Display for Adapter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Spin => "spin",
+            Self::Preview1 => "preview1",
+            Self::Command => "command",
+        })
     }
 }
 
+/// An import which was renamed during componentization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RewrittenImport {
+    /// The module name of the original import
+    pub module: String,
+    /// The field name of the original import
+    pub name: String,
+    /// The module name of the rewritten import
+    pub new_module: String,
+    /// The field name of the rewritten import
+    pub new_name: String,
+}
+
 /// In order to properly componentize modules, we need to know which
 /// version of wit-bindgen was used
-#[derive(Debug)]
-enum WitBindgenVersion {
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum WitBindgenVersion {
     GreaterThanV0_4,
+    V0_3OrV0_4,
     V0_2,
     Other(String),
 }
 
 impl WitBindgenVersion {
-    fn from_module(module: &[u8]) -> Result<Self> {
-        let (_, bindgen) = metadata::decode(module)?;
-        if let Some(producers) = bindgen.producers {
-            if let Some(processors) = producers.get("processed-by") {
-                let bindgen_version = processors.iter().find_map(|(key, value)| {
-                    key.starts_with("wit-bindgen").then(|| value.as_str())
-                });
-                if let Some(v) = bindgen_version {
-                    let mut parts = v.split('.');
-                    let Some(major) = parts.next().and_then(|p| p.parse::<u8>().ok()) else {
-                        return Ok(Self::Other(v.to_owned()));
-                    };
-                    let Some(minor) = parts.next().and_then(|p| p.parse::<u8>().ok()) else {
-                        return Ok(Self::Other(v.to_owned()));
-                    };
-                    if (major == 0 && minor < 5) || major >= 1 {
-                        return Ok(Self::Other(v.to_owned()));
-                    }
-                    // Either there should be no patch version or nothing after patch
-                    if parts.next().is_none() || parts.next().is_none() {
-                        return Ok(Self::GreaterThanV0_4);
-                    } else {
-                        return Ok(Self::Other(v.to_owned()));
+    /// Detect the wit-bindgen version from the `producers` metadata of the given module
+    pub fn from_module(module: &[u8]) -> Result<Self> {
+        match Parser::new(0).parse_all(module).next() {
+            Some(Ok(Payload::Version { .. })) => (),
+            Some(Err(e)) => return Err(e.into()),
+            _ => return Err(ComponentizeError::UnknownEncoding),
+        }
+        check_sections(module)?;
+
+        let bindgen = match metadata::decode(module) {
+            Ok((_, bindgen)) => bindgen,
+            Err(e) => {
+                return match component_type_sections(module)? {
+                    // wit-bindgen 0.5 and newer record themselves in the `producers` section of the component
+                    // they embed as metadata, so we can tell when that metadata uses a format newer than the one
+                    // wit-component understands
+                    ComponentTypeSections::Encoded(processors) => {
+                        match Self::from_processors(
+                            processors.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+                        ) {
+                            Self::GreaterThanV0_4 => Err(ComponentizeError::Metadata(e.context(
+                                "component type metadata uses a format newer than this crate supports",
+                            ))),
+                            // Without a wit-bindgen producer, this can only be a release older than 0.5
+                            Self::V0_2 => Ok(Self::V0_3OrV0_4),
+                            version => Ok(version),
+                        }
                     }
-                }
+                    // wit-bindgen 0.3 and 0.4 embedded component type metadata in a format which predates the
+                    // one wit-component understands, and did not record themselves in the `producers` metadata
+                    ComponentTypeSections::Legacy => Ok(Self::V0_3OrV0_4),
+                    ComponentTypeSections::None => Err(ComponentizeError::Metadata(e)),
+                };
+            }
+        };
+        Ok(bindgen
+            .producers
+            .as_ref()
+            .and_then(|producers| producers.get("processed-by"))
+            .map(|processors| {
+                Self::from_processors(
+                    processors
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), value.as_str())),
+                )
+            })
+            .unwrap_or(Self::V0_2))
+    }
+
+    /// Detect the wit-bindgen version from the `processed-by` entries of a `producers` section
+    fn from_processors<'a>(processors: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let processors = processors.into_iter().collect::<Vec<_>>();
+        let bindgen_version = processors
+            .iter()
+            .find_map(|(key, value)| key.starts_with("wit-bindgen").then_some(*value));
+        if let Some(v) = bindgen_version {
+            let mut parts = v.split('.');
+            let Some(major) = parts.next().and_then(|p| p.parse::<u8>().ok()) else {
+                return Self::Other(v.to_owned());
+            };
+            let Some(minor) = parts.next().and_then(|p| p.parse::<u8>().ok()) else {
+                return Self::Other(v.to_owned());
+            };
+            let version = match (major, minor) {
+                (0, 3 | 4) => Self::V0_3OrV0_4,
+                // wit-bindgen 0.5 settled on the canonical ABI, which later releases (including 1.x) keep using;
+                // any changes to the metadata format are handled by wit-component
+                (0, 5..) | (1.., _) => Self::GreaterThanV0_4,
+                _ => return Self::Other(v.to_owned()),
+            };
+            // Either there should be no patch version or nothing after patch
+            if parts.next().is_none() || parts.next().is_none() {
+                return version;
+            } else {
+                return Self::Other(v.to_owned());
             }
         }
 
-        Ok(Self::V0_2)
+        // These toolchains generate their own bindings and have only ever targeted the new ABI
+        if processors
+            .iter()
+            .any(|(key, _)| NEW_ABI_PRODUCERS.contains(key))
+        {
+            return Self::GreaterThanV0_4;
+        }
+
+        Self::V0_2
+    }
+}
+
+impl fmt::Display for WitBindgenVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GreaterThanV0_4 => f.write_str(">=0.5"),
+            Self::V0_3OrV0_4 => f.write_str("0.3/0.4"),
+            Self::V0_2 => f.write_str("0.2"),
+            Self::Other(other) => write!(f, "unsupported ({other})"),
+        }
     }
 }
 
 /// Modules produced with wit-bindgen 0.5 and newer only need wasi preview 1 to preview 2 adapter
 pub fn componentize_new_bindgen(module: &[u8]) -> Result<Vec<u8>> {
-    ComponentEncoder::default()
+    componentize_new_bindgen_with_report(
+        module,
+        required_adapter(Adapter::Preview1, WasiVersion::default())?,
+    )
+    .map(|(component, _)| component)
+}
+
+fn componentize_new_bindgen_with_report(
+    module: &[u8],
+    preview1_adapter: &[u8],
+) -> Result<(Vec<u8>, ComponentizeReport)> {
+    let component = ComponentEncoder::default()
         .validate(true)
-        .module(&module)?
-        .adapter("wasi_snapshot_preview1", PREVIEW1_ADAPTER)?
+        .module(&module)
+        .map_err(ComponentizeError::Metadata)?
+        .adapter("wasi_snapshot_preview1", preview1_adapter)
+        .map_err(ComponentizeError::Metadata)?
         .encode()
+        .map_err(ComponentizeError::Encode)?;
+
+    let report = ComponentizeReport {
+        wit_bindgen_version: None,
+        abi: Abi::NewBindgen,
+        adapter: Adapter::Preview1,
+        rewritten_imports: Vec::new(),
+        kept_exports: Vec::new(),
+        dropped_exports: Vec::new(),
+        warnings: Vec::new(),
+        input_size: module.len(),
+        output_size: component.len(),
+    };
+
+    Ok((component, report))
 }
 
 /// Modules produced with wit-bindgen 0.2 need more extensive adaption
 pub fn componentize_old_bindgen(module: &[u8]) -> Result<Vec<u8>> {
-    let (module, exports) = retarget_imports_and_get_exports(ADAPTER_NAME, module)?;
+    componentize_old_bindgen_with_report(
+        module,
+        &DecodedSpinAdapter::decode(required_adapter(Adapter::Spin, WasiVersion::default())?)?,
+        SpinWorld::default(),
+        &ExportRegistry::default(),
+        false,
+    )
+    .map(|(component, _)| component)
+}
+
+fn componentize_old_bindgen_with_report(
+    module: &[u8],
+    spin: &DecodedSpinAdapter,
+    spin_world: SpinWorld,
+    registry: &ExportRegistry,
+    strict: bool,
+) -> Result<(Vec<u8>, ComponentizeReport)> {
+    let input_size = module.len();
+    let (module, exports, rewritten_imports) =
+        retarget_imports_and_get_exports(ADAPTER_NAME, module)?;
+    let (component, mut report) = componentize_retargeted_old_bindgen(
+        &module,
+        &exports,
+        rewritten_imports,
+        spin,
+        spin_world,
+        registry,
+        strict,
+    )?;
+    report.input_size = input_size;
+    Ok((component, report))
+}
+
+/// The part of [`componentize_old_bindgen_with_report`] which follows moving the imports of `module` to the adapter
+/// and finding its function `exports`
+pub(crate) fn componentize_retargeted_old_bindgen(
+    module: &[u8],
+    exports: &[String],
+    rewritten_imports: Vec<RewrittenImport>,
+    spin: &DecodedSpinAdapter,
+    spin_world: SpinWorld,
+    registry: &ExportRegistry,
+    strict: bool,
+) -> Result<(Vec<u8>, ComponentizeReport)> {
+    // The 2.0 worlds have nowhere to export anything besides the HTTP handler
+    let unregistered = ExportRegistry::default();
+    let (export_interfaces, registry) = match spin_world {
+        SpinWorld::Reactor => (EXPORT_INTERFACES, registry),
+        SpinWorld::V2_0_0 => (spin_world::V2_EXPORT_INTERFACES, &unregistered),
+    };
+    let diagnostics = ExportDiagnostics::new(
+        exports,
+        registry
+            .exports()
+            .chain(export_interfaces.iter().map(|(k, _)| *k)),
+    );
+    if strict && !diagnostics.unrecognized.is_empty() {
+        return Err(ComponentizeError::UnrecognizedExports(diagnostics));
+    }
+    let warnings = diagnostics
+        .unrecognized
+        .iter()
+        .map(|export| format!("dropped unrecognized export {export}"))
+        .collect();
+
+    // An ordered set keeps the output reproducible should anything come to depend on its iteration order
     let allowed = exports
-        .into_iter()
+        .iter()
         .filter_map(|export| {
-            EXPORT_INTERFACES
-                .iter()
-                .find_map(|(k, v)| (*k == &export).then_some(*v))
+            registry.interface(export).or_else(|| {
+                export_interfaces
+                    .iter()
+                    .find_map(|(k, v)| (*k == export.as_str()).then_some(*v))
+            })
         })
-        .collect::<HashSet<&str>>();
+        .collect::<BTreeSet<&str>>();
 
-    let (adapter, mut bindgen) = metadata::decode(SPIN_ADAPTER)?;
+    let world = spin.world(WORLD_NAME)?;
+    let prepared = spin.prepare(world, registry, true, &allowed)?;
 
-    let world = bindgen
-        .resolve
-        .worlds
-        .iter()
-        .find_map(|(k, v)| (v.name == WORLD_NAME).then_some(k))
-        .ok_or_else(|| anyhow!("world not found: {WORLD_NAME}"))?;
-
-    bindgen.resolve.worlds[world].exports.retain(|k, _| {
-        let k = match &k {
-            wit_parser::WorldKey::Name(n) => n,
-            wit_parser::WorldKey::Interface(i) => match &bindgen.resolve.interfaces[*i].name {
-                Some(n) => n,
-                None => return true,
-            },
-        };
-        allowed.contains(k.as_str())
-    });
+    let component = ComponentEncoder::default()
+        .validate(true)
+        .module(module)
+        .map_err(ComponentizeError::Metadata)?
+        .adapter(
+            ADAPTER_NAME,
+            prepared
+                .adapter
+                .as_deref()
+                .expect("adapter should be embedded"),
+        )
+        .map_err(ComponentizeError::Metadata)?
+        .encode()
+        .map_err(ComponentizeError::Encode)?;
+    let component = match spin_world {
+        SpinWorld::Reactor => component,
+        SpinWorld::V2_0_0 => spin_world::compose_v2(&component)?,
+    };
 
-    let body = metadata::encode(
-        &bindgen.resolve,
-        world,
-        wit_component::StringEncoding::UTF8,
-        None,
-    )?;
+    let report = ComponentizeReport {
+        wit_bindgen_version: None,
+        abi: Abi::OldBindgen,
+        adapter: Adapter::Spin,
+        rewritten_imports,
+        kept_exports: prepared.kept_exports.clone(),
+        dropped_exports: prepared.dropped_exports.clone(),
+        warnings,
+        input_size: module.len(),
+        output_size: component.len(),
+    };
 
-    let adapter = add_custom_section(CUSTOM_SECTION_NAME, &body, &adapter)?;
+    Ok((component, report))
+}
+
+/// Modules produced with wit-bindgen 0.3 and 0.4 already use the canonical ABI, but they name their imports and
+/// exports after the bare interface names of their world and embed component type metadata in a format which
+/// wit-component no longer understands.  We rename those imports and exports to the fully qualified
+/// `fermyon:spin` interfaces of the Spin adapter's world and replace the metadata with that world.
+pub fn componentize_v0_4_bindgen(module: &[u8]) -> Result<Vec<u8>> {
+    componentize_v0_4_bindgen_with_report(
+        module,
+        &DecodedSpinAdapter::decode(required_adapter(Adapter::Spin, WasiVersion::default())?)?,
+        required_adapter(Adapter::Preview1, WasiVersion::default())?,
+    )
+    .map(|(component, _)| component)
+}
+
+fn componentize_v0_4_bindgen_with_report(
+    module: &[u8],
+    spin: &DecodedSpinAdapter,
+    preview1_adapter: &[u8],
+) -> Result<(Vec<u8>, ComponentizeReport)> {
+    let input_size = module.len();
+    // Only the Spin adapter's world is needed; the module itself is adapted with the upstream adapter
+    let world = spin.world(WORLD_NAME)?;
+    let resolve = &spin.resolve;
+
+    let world_item = &resolve.worlds[world];
+    let interfaces = world_item
+        .imports
+        .keys()
+        .chain(world_item.exports.keys())
+        .filter_map(|key| match key {
+            wit_parser::WorldKey::Interface(id) => {
+                Some((resolve.interfaces[*id].name.clone()?, resolve.id_of(*id)?))
+            }
+            wit_parser::WorldKey::Name(_) => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let (mut module, exports, rewritten_imports) = qualify_interface_names(&interfaces, module)?;
+    let allowed = exports.iter().map(String::as_str).collect::<BTreeSet<_>>();
+    let prepared = spin.prepare(world, &ExportRegistry::default(), false, &allowed)?;
 
-    Ok(ComponentEncoder::default()
+    // Appending to the module before it is finished saves copying it all over again
+    module.section(&CustomSection {
+        name: Cow::Borrowed(CUSTOM_SECTION_NAME),
+        data: Cow::Borrowed(&prepared.metadata),
+    });
+    let module = module.finish();
+
+    let component = ComponentEncoder::default()
         .validate(true)
-        .module(&module)?
-        .adapter(ADAPTER_NAME, &adapter)?
-        .encode()?)
+        .module(&module)
+        .map_err(ComponentizeError::Metadata)?
+        .adapter(ADAPTER_NAME, preview1_adapter)
+        .map_err(ComponentizeError::Metadata)?
+        .encode()
+        .map_err(ComponentizeError::Encode)?;
+
+    let report = ComponentizeReport {
+        wit_bindgen_version: None,
+        abi: Abi::V0_4Bindgen,
+        adapter: Adapter::Preview1,
+        rewritten_imports,
+        kept_exports: prepared.kept_exports.clone(),
+        dropped_exports: prepared.dropped_exports.clone(),
+        warnings: Vec::new(),
+        input_size,
+        output_size: component.len(),
+    };
+
+    Ok((component, report))
+}
+
+/// The kind of `component-type` custom sections found in a module
+enum ComponentTypeSections {
+    /// At least one section contains a component binary, along with the `processed-by` entries of the `producers`
+    /// sections found in those components
+    Encoded(Vec<(String, String)>),
+    /// The sections use a format which predates embedding a component binary
+    Legacy,
+    /// There are no such sections
+    None,
+}
+
+fn component_type_sections(module: &[u8]) -> Result<ComponentTypeSections> {
+    let mut result = ComponentTypeSections::None;
+    for payload in Parser::new(0).parse_all(module) {
+        let Payload::CustomSection(section) = payload? else {
+            continue;
+        };
+        if !section.name().starts_with("component-type") {
+            continue;
+        }
+
+        if !wasmparser::Parser::is_component(section.data()) {
+            if let ComponentTypeSections::None = result {
+                result = ComponentTypeSections::Legacy;
+            }
+            continue;
+        }
+
+        let mut processors = match result {
+            ComponentTypeSections::Encoded(processors) => processors,
+            _ => Vec::new(),
+        };
+        // Only look at the top level of the embedded component; a section which fails to parse tells us nothing
+        // more about its producers
+        let mut depth = 0;
+        for payload in Parser::new(0).parse_all(section.data()) {
+            match payload {
+                Ok(Payload::CustomSection(custom))
+                    if depth == 0 && custom.name() == "producers" =>
+                {
+                    let Ok(fields) = wasmparser::ProducersSectionReader::new(
+                        custom.data(),
+                        custom.data_offset(),
+                    ) else {
+                        break;
+                    };
+                    for field in fields.into_iter().flatten() {
+                        if field.name == "processed-by" {
+                            for value in field.values.into_iter().flatten() {
+                                processors.push((value.name.to_owned(), value.version.to_owned()));
+                            }
+                        }
+                    }
+                }
+                Ok(Payload::ModuleSection { .. } | Payload::ComponentSection { .. }) => depth += 1,
+                Ok(Payload::End(_)) => depth -= 1,
+                Ok(_) => (),
+                Err(_) => break,
+            }
+        }
+        result = ComponentTypeSections::Encoded(processors);
+    }
+    Ok(result)
 }
 
 pub fn componentize_command(module: &[u8]) -> Result<Vec<u8>> {
-    Ok(ComponentEncoder::default()
+    componentize_command_with_report(
+        module,
+        required_adapter(Adapter::Command, WasiVersion::default())?,
+    )
+    .map(|(component, _)| component)
+}
+
+fn componentize_command_with_report(
+    module: &[u8],
+    command_adapter: &[u8],
+) -> Result<(Vec<u8>, ComponentizeReport)> {
+    let component = ComponentEncoder::default()
         .validate(true)
-        .module(&module)?
-        .adapter(ADAPTER_NAME, COMMAND_ADAPTER)?
-        .encode()?)
+        .module(&module)
+        .map_err(ComponentizeError::Metadata)?
+        .adapter(ADAPTER_NAME, command_adapter)
+        .map_err(ComponentizeError::Metadata)?
+        .encode()
+        .map_err(ComponentizeError::Encode)?;
+
+    let report = ComponentizeReport {
+        wit_bindgen_version: None,
+        abi: Abi::Command,
+        adapter: Adapter::Command,
+        rewritten_imports: Vec::new(),
+        kept_exports: Vec::new(),
+        dropped_exports: Vec::new(),
+        warnings: Vec::new(),
+        input_size: module.len(),
+        output_size: component.len(),
+    };
+
+    Ok((component, report))
+}
+
+/// Move every import of `module` to the `target` module, returning the new module along with the names of the
+/// functions it exports and the imports which were moved
+fn retarget_imports_and_get_exports(
+    target: &str,
+    module: &[u8],
+) -> Result<(Vec<u8>, Vec<String>, Vec<RewrittenImport>)> {
+    let mut scanned = ScannedModule::read(&[], module, |_| false)?;
+    let rewritten_imports = scanned.retarget_imports(target)?;
+    Ok((scanned.module, scanned.exports, rewritten_imports))
 }
 
-fn retarget_imports_and_get_exports(target: &str, module: &[u8]) -> Result<(Vec<u8>, Vec<String>)> {
+/// Move every import to the `target` module, recording those which were moved in `rewritten_imports`
+///
+/// The field name of a moved import becomes `<module>:<field>`.
+pub(crate) fn retarget_imports(
+    target: &str,
+    reader: wasmparser::ImportSectionReader,
+    rewritten_imports: &mut Vec<RewrittenImport>,
+) -> Result<ImportSection> {
+    let mut imports = ImportSection::new();
+    for import in reader.into_iter_with_offsets() {
+        let (offset, import) = import?;
+        let (module, field) = if import.module == target {
+            (Cow::Borrowed(import.module), Cow::Borrowed(import.name))
+        } else {
+            let field = format!("{}:{}", import.module, import.name);
+            rewritten_imports.push(RewrittenImport {
+                module: import.module.to_owned(),
+                name: import.name.to_owned(),
+                new_module: target.to_owned(),
+                new_name: field.clone(),
+            });
+            (Cow::Borrowed(target), Cow::Owned(field))
+        };
+        imports.import(&module, &field, entity_type(&import, offset)?);
+    }
+    Ok(imports)
+}
+
+/// Rename imports and exports which refer to one of the bare interface names in `interfaces` to the corresponding
+/// fully qualified name, dropping any existing component type metadata.
+///
+/// Post-return exports (`cabi_post_<interface>#<function>`) are renamed along with the functions they belong to.
+///
+/// Returns the new module, the bare names of the interfaces the module exports, and the renamed imports.
+fn qualify_interface_names(
+    interfaces: &BTreeMap<String, String>,
+    module: &[u8],
+) -> Result<(Module, Vec<String>, Vec<RewrittenImport>)> {
     let mut result = Module::new();
     let mut exports_result = Vec::new();
+    let mut rewritten_imports = Vec::new();
 
     for payload in Parser::new(0).parse_all(module) {
         match payload? {
             Payload::ImportSection(reader) => {
                 let mut imports = ImportSection::new();
-                for import in reader {
-                    let import = import?;
-                    let (module, field) = if import.module == target {
-                        (Cow::Borrowed(import.module), Cow::Borrowed(import.name))
-                    } else {
-                        (
-                            Cow::Borrowed(target),
-                            Cow::Owned(format!("{}:{}", import.module, import.name)),
-                        )
+                for import in reader.into_iter_with_offsets() {
+                    let (offset, import) = import?;
+                    let module = match interfaces.get(import.module) {
+                        Some(qualified) => {
+                            rewritten_imports.push(RewrittenImport {
+                                module: import.module.to_owned(),
+                                name: import.name.to_owned(),
+                                new_module: qualified.clone(),
+                                new_name: import.name.to_owned(),
+                            });
+                            qualified.as_str()
+                        }
+                        None => import.module,
                     };
-                    imports.import(&module, &field, IntoEntityType(import.ty));
+                    imports.import(module, import.name, entity_type(&import, offset)?);
                 }
                 result.section(&imports);
             }
 
             Payload::ExportSection(reader) => {
                 let mut exports = ExportSection::new();
-                for export in reader {
-                    let export = export?;
-                    exports_result.push(export.name.to_owned());
-                    exports.export(
-                        export.name,
-                        IntoExportKind(export.kind).into(),
-                        export.index,
-                    );
+                for export in reader.into_iter_with_offsets() {
+                    let (offset, export) = export?;
+                    // Post-return functions carry the same interface name behind a `cabi_post_` prefix
+                    let (prefix, unprefixed) = match export.name.strip_prefix(POST_RETURN_PREFIX) {
+                        Some(name) => (POST_RETURN_PREFIX, name),
+                        None => ("", export.name),
+                    };
+                    let name = match unprefixed
+                        .split_once('#')
+                        .and_then(|(interface, function)| {
+                            Some((interface, interfaces.get(interface)?, function))
+                        }) {
+                        Some((interface, qualified, function)) => {
+                            if !exports_result.iter().any(|e| e == interface) {
+                                exports_result.push(interface.to_owned());
+                            }
+                            Cow::Owned(format!("{prefix}{qualified}#{function}"))
+                        }
+                        None => Cow::Borrowed(export.name),
+                    };
+                    exports.export(&name, export_kind(&export, offset)?, export.index);
                 }
                 result.section(&exports);
             }
 
+            Payload::CustomSection(section) if section.name().starts_with("component-type") => (),
+
             payload => {
                 if let Some((id, range)) = payload.as_section() {
                     result.section(&RawSection {
                         id,
-                        data: &module[range],
+                        data: section_data(module, range)?,
                     });
                 }
             }
         }
     }
 
-    Ok((result.finish(), exports_result))
+    Ok((result, exports_result, rewritten_imports))
+}
+
+/// Convert the type of an import found at `offset` for re-encoding
+fn entity_type(import: &wasmparser::Import, offset: usize) -> Result<wasm_encoder::EntityType> {
+    IntoEntityType(import.ty)
+        .try_into()
+        .map_err(
+            |e: convert::UnsupportedType| ComponentizeError::Unsupported {
+                construct: e.0,
+                section: "import",
+                item: format!("{}::{}", import.module, import.name),
+                offset,
+            },
+        )
+}
+
+/// Convert the kind of an export found at `offset` for re-encoding
+fn export_kind(export: &wasmparser::Export, offset: usize) -> Result<wasm_encoder::ExportKind> {
+    IntoExportKind(export.kind)
+        .try_into()
+        .map_err(
+            |e: convert::UnsupportedType| ComponentizeError::Unsupported {
+                construct: e.0,
+                section: "export",
+                item: export.name.to_owned(),
+                offset,
+            },
+        )
 }
 
 fn add_custom_section(name: &str, data: &[u8], module: &[u8]) -> Result<Vec<u8>> {
@@ -224,7 +764,7 @@ fn add_custom_section(name: &str, data: &[u8], module: &[u8]) -> Result<Vec<u8>>
         if let Some((id, range)) = payload?.as_section() {
             result.section(&RawSection {
                 id,
-                data: &module[range],
+                data: section_data(module, range)?,
             });
         }
     }
@@ -237,6 +777,29 @@ fn add_custom_section(name: &str, data: &[u8], module: &[u8]) -> Result<Vec<u8>>
     Ok(result.finish())
 }
 
+/// The contents of the section at `range`, which wasmparser reports before reading them and so may extend past the
+/// end of a truncated binary
+pub(crate) fn section_data(binary: &[u8], range: Range<usize>) -> Result<&[u8]> {
+    binary.get(range).ok_or_else(|| {
+        // Only wasmparser can create a `BinaryReaderError`, so have it report the missing bytes itself
+        ComponentizeError::InvalidBinary(
+            wasmparser::BinaryReader::new_with_offset(&[], binary.len())
+                .read_u8()
+                .expect_err("an empty reader has no bytes to read"),
+        )
+    })
+}
+
+/// Check that every section of `binary` is complete, which wit-component assumes when it copies them
+pub(crate) fn check_sections(binary: &[u8]) -> Result<()> {
+    for payload in Parser::new(0).parse_all(binary) {
+        if let Some((_, range)) = payload?.as_section() {
+            section_data(binary, range)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::{path::PathBuf, process};
@@ -247,8 +810,8 @@ mod tests {
     use {
         anyhow::{anyhow, Result},
         spin_abi_conformance::{
-            InvocationStyle, KeyValueReport, LlmReport, MysqlReport, PostgresReport, RedisReport,
-            Report, TestConfig, WasiReport,
+            v2, InvocationStyle, KeyValueReport, LlmReport, MysqlReport, PostgresReport,
+            RedisReport, Report, SqliteReport, TestConfig, WasiReport, World, WorldReport,
         },
         tokio::fs,
         wasmtime::{
@@ -262,31 +825,61 @@ mod tests {
     };
 
     async fn run_spin(module: &[u8]) -> Result<()> {
+        run_spin_with_style(module, InvocationStyle::InboundHttp).await
+    }
+
+    async fn run_spin_with_style(module: &[u8], invocation_style: InvocationStyle) -> Result<()> {
         let mut config = Config::new();
         config.wasm_component_model(true);
         config.async_support(true);
 
         let engine = Engine::new(&config)?;
 
-        let component = Component::new(
-            &engine,
-            crate::componentize(module).context("could not componentize")?,
-        )
-        .context("failed to instantiate componentized bytes")?;
+        // Auto-detection picks the ABI wit-bindgen modules are built for, so commands have to ask for theirs
+        let component = match invocation_style {
+            InvocationStyle::Command => crate::ComponentizeOptions::new()
+                .abi(crate::Abi::Command)
+                .componentize(module),
+            _ => crate::componentize(module),
+        };
+        let component = Component::new(&engine, component.context("could not componentize")?)
+            .context("failed to instantiate componentized bytes")?;
 
         let report = spin_abi_conformance::test(
             &component,
             &engine,
             TestConfig {
-                invocation_style: InvocationStyle::InboundHttp,
+                invocation_style,
+                world: World::Host,
             },
         )
         .await
         .context("abi conformance test failed")?;
 
+        // A command exports no triggers and a `wasi:http` guest no `inbound-redis`, so the tests which call them
+        // directly rather than through `invocation_style` are expected to fail for lack of the export.  Only the
+        // first line of the error is compared, since the rest is a backtrace if `RUST_BACKTRACE` is set.
+        let missing = |result: &Result<(), String>, name: &str| {
+            let missing = format!("no {name} instance found");
+            match result {
+                Err(e) if e.lines().next() == Some(missing.as_str()) => result.clone(),
+                _ => Err(format!("expected {missing:?}, got {result:?}")),
+            }
+        };
+
         let expected = Report {
-            inbound_http: Ok(()),
-            inbound_redis: Ok(()),
+            inbound_http: match invocation_style {
+                InvocationStyle::Command => {
+                    missing(&report.inbound_http, "fermyon:spin/inbound-http")
+                }
+                _ => Ok(()),
+            },
+            inbound_redis: match invocation_style {
+                InvocationStyle::Command | InvocationStyle::WasiHttp => {
+                    missing(&report.inbound_redis, "fermyon:spin/inbound-redis")
+                }
+                _ => Ok(()),
+            },
             config: Ok(()),
             http: Ok(()),
             redis: RedisReport {
@@ -308,6 +901,13 @@ mod tests {
                 execute: Ok(()),
                 query: Ok(()),
             },
+            sqlite: SqliteReport {
+                open: Ok(()),
+                open_no_such_database: Ok(()),
+                open_access_denied: Ok(()),
+                execute: Ok(()),
+                close: Ok(()),
+            },
             key_value: KeyValueReport {
                 open: Ok(()),
                 get: Ok(()),
@@ -317,7 +917,11 @@ mod tests {
                 get_keys: Ok(()),
                 close: Ok(()),
             },
-            llm: LlmReport { infer: Ok(()) },
+            llm: LlmReport {
+                infer: Ok(()),
+                infer_with_params: Ok(()),
+                embeddings: Ok(()),
+            },
             wasi: WasiReport {
                 env: Ok(()),
                 epoch: Ok(()),
@@ -336,6 +940,79 @@ mod tests {
         }
     }
 
+    async fn run_spin_platform(module: &[u8]) -> Result<()> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+
+        let engine = Engine::new(&config)?;
+
+        let component = Component::new(
+            &engine,
+            crate::componentize(module).context("could not componentize")?,
+        )
+        .context("failed to instantiate componentized bytes")?;
+
+        let report = spin_abi_conformance::test_world(
+            &component,
+            &engine,
+            TestConfig {
+                invocation_style: InvocationStyle::WasiHttp,
+                world: World::Platform2_0_0,
+            },
+        )
+        .await
+        .context("abi conformance test failed")?;
+
+        let expected = WorldReport::Platform2_0_0(Box::new(v2::Report {
+            inbound_http: Ok(()),
+            variables: Ok(()),
+            http: Ok(()),
+            key_value: v2::KeyValueReport {
+                open: Ok(()),
+                get: Ok(()),
+                set: Ok(()),
+                delete: Ok(()),
+                exists: Ok(()),
+                get_keys: Ok(()),
+            },
+            redis: v2::RedisReport {
+                open: Ok(()),
+                publish: Ok(()),
+                set: Ok(()),
+                get: Ok(()),
+                incr: Ok(()),
+                del: Ok(()),
+                sadd: Ok(()),
+                srem: Ok(()),
+                smembers: Ok(()),
+                execute: Ok(()),
+            },
+            postgres: v2::PostgresReport {
+                open: Ok(()),
+                execute: Ok(()),
+                query: Ok(()),
+            },
+            mysql: v2::MysqlReport {
+                open: Ok(()),
+                execute: Ok(()),
+                query: Ok(()),
+            },
+            sqlite: v2::SqliteReport {
+                open: Ok(()),
+                open_no_such_database: Ok(()),
+                open_access_denied: Ok(()),
+                execute: Ok(()),
+            },
+        }));
+
+        if report == expected {
+            Ok(())
+        } else {
+            Err(anyhow!("{report:#?}"))
+        }
+    }
+
     async fn run_command(module: &[u8]) -> Result<()> {
         let mut config = Config::new();
         config.wasm_component_model(true);
@@ -410,50 +1087,1433 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn rust_wit_bindgen_08() -> Result<()> {
-        build_rust_test_case("rust-case-0.8");
+    async fn rust_wit_bindgen_04() -> Result<()> {
+        build_rust_test_case("rust-case-0.4");
         run_spin(
             &fs::read(concat!(
                 env!("OUT_DIR"),
-                "/wasm32-wasi/release/rust_case_08.wasm"
+                "/wasm32-wasi/release/rust_case_04.wasm"
             ))
             .await?,
         )
         .await
     }
 
-    #[ignore]
-    #[tokio::test]
-    async fn go() -> Result<()> {
-        let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
-        let mut cmd = process::Command::new("tinygo");
-        cmd.arg("build")
-            .current_dir("tests/go-case")
-            .arg("-target=wasi")
-            .arg("-gc=leaking")
-            .arg("-no-debug")
-            .arg("-o")
-            .arg(out_dir.join("go_case.wasm"))
-            .arg("main.go");
+    #[test]
+    fn wit_bindgen_04_post_return() -> Result<()> {
+        // The exports wit-bindgen 0.4 generates for `inbound-http`, including the post-return function which frees
+        // the response
+        let module = wat::parse_str(
+            r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+              (func (export "inbound-http#handle-request")
+                (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32) unreachable)
+              (func (export "cabi_post_inbound-http#handle-request") (param i32))
+            )
+            "#,
+        )?;
 
-        // If just skip this if TinyGo is not installed
-        _ = cmd.status();
-        run_spin(&fs::read(concat!(env!("OUT_DIR"), "/go_case.wasm")).await?).await
+        let component = crate::componentize_v0_4_bindgen(&module)?;
+
+        let mut post_returns = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&component) {
+            if let wasmparser::Payload::ComponentCanonicalSection(reader) = payload? {
+                for function in reader {
+                    if let wasmparser::CanonicalFunction::Lift { options, .. } = function? {
+                        post_returns += options
+                            .iter()
+                            .filter(|option| {
+                                matches!(option, wasmparser::CanonicalOption::PostReturn(_))
+                            })
+                            .count();
+                    }
+                }
+            }
+        }
+        assert_eq!(1, post_returns);
+
+        Ok(())
     }
 
     #[tokio::test]
-    async fn rust_command() -> Result<()> {
-        build_rust_test_case("rust-command");
-        run_command(
+    async fn rust_wit_bindgen_08() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        run_spin(
             &fs::read(concat!(
                 env!("OUT_DIR"),
-                "/wasm32-wasi/release/rust-command.wasm"
+                "/wasm32-wasi/release/rust_case_08.wasm"
+            ))
+            .await?,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_019() -> Result<()> {
+        build_rust_test_case("rust-case-0.19");
+        run_spin(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_019.wasm"
             ))
             .await?,
         )
         .await
     }
 
+    #[tokio::test]
+    async fn rust_wit_bindgen_019_inbound_redis() -> Result<()> {
+        build_rust_test_case("rust-case-0.19");
+        run_spin_with_style(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_019.wasm"
+            ))
+            .await?,
+            InvocationStyle::InboundRedis,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_019_command() -> Result<()> {
+        build_rust_test_case("rust-case-0.19-command");
+        run_spin_with_style(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust-case-019-command.wasm"
+            ))
+            .await?,
+            InvocationStyle::Command,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_019_wasi_http() -> Result<()> {
+        build_rust_test_case("rust-case-0.19-wasi-http");
+        run_spin_with_style(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_019_wasi_http.wasm"
+            ))
+            .await?,
+            InvocationStyle::WasiHttp,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_019_platform() -> Result<()> {
+        build_rust_test_case("rust-case-0.19-platform");
+        run_spin_platform(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_019_platform.wasm"
+            ))
+            .await?,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn spin_world_v2() -> Result<()> {
+        // A wit-bindgen 0.2 module answering the inbound HTTP test at "/foo" and, at "/", looking up the config
+        // key following "variables%20" in the body as the variables test asks
+        let module = wat::parse_str(
+            r#"
+            (module
+              (import "spin-config" "get-config" (func $get-config (param i32 i32 i32)))
+              ;; The adapter allocates its state, which is larger than a page, from this never-growing heap
+              (memory (export "memory") 4)
+              (global $heap (mut i32) (i32.const 1024))
+              (data (i32.const 0) "lorem")
+              (data (i32.const 8) "ipsum")
+              (data (i32.const 16) "dolor sit amet")
+              ;; The "lorem: ipsum" header
+              (data (i32.const 32) "\00\00\00\00\05\00\00\00\08\00\00\00\05\00\00\00")
+              (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+                (local $ptr i32)
+                global.get $heap
+                i32.const 7
+                i32.add
+                i32.const -8
+                i32.and
+                local.tee $ptr
+                local.get 3
+                i32.add
+                global.set $heap
+                local.get $ptr)
+              (func (export "canonical_abi_free") (param i32 i32 i32))
+              (func (export "handle-redis-message") (param i32 i32) (result i32)
+                unreachable)
+              (func (export "handle-http-request")
+                (param $method i32) (param $uri i32) (param $uri-len i32) (param i32 i32 i32 i32)
+                (param $has-body i32) (param $body i32) (param $body-len i32) (result i32)
+                ;; The response record is built at 64, and the config lookup result at 96
+                local.get $uri-len
+                i32.const 1
+                i32.eq
+                if
+                  local.get $body
+                  i32.const 12
+                  i32.add
+                  local.get $body-len
+                  i32.const 12
+                  i32.sub
+                  i32.const 96
+                  call $get-config
+                  i32.const 64
+                  i32.const 200
+                  i32.const 500
+                  i32.const 96
+                  i32.load8_u
+                  i32.eqz
+                  select
+                  i32.store16
+                  i32.const 68
+                  i32.const 0
+                  i32.store8
+                  i32.const 80
+                  i32.const 0
+                  i32.store8
+                else
+                  i32.const 64
+                  i32.const 200
+                  i32.store16
+                  i32.const 68
+                  i32.const 1
+                  i32.store8
+                  i32.const 72
+                  i32.const 32
+                  i32.store
+                  i32.const 76
+                  i32.const 1
+                  i32.store
+                  i32.const 80
+                  i32.const 1
+                  i32.store8
+                  i32.const 84
+                  i32.const 16
+                  i32.store
+                  i32.const 88
+                  i32.const 14
+                  i32.store
+                end
+                i32.const 64)
+            )
+            "#,
+        )?;
+
+        let (component, report) = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::OldBindgen)
+            .spin_world(crate::SpinWorld::V2_0_0)
+            .componentize_with_report(&module)?;
+        assert_eq!(
+            vec!["dropped unrecognized export `handle-redis-message`"],
+            report.warnings
+        );
+
+        let mut imports = Vec::new();
+        let mut exports = Vec::new();
+        // Only the outermost component's imports and exports are of interest, not those of the components nested in it
+        let mut depth = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&component) {
+            match payload? {
+                wasmparser::Payload::Version { .. } => depth += 1,
+                wasmparser::Payload::End(_) => depth -= 1,
+                wasmparser::Payload::ComponentImportSection(reader) if depth == 1 => {
+                    for import in reader {
+                        imports.push(import?.name.0.to_owned());
+                    }
+                }
+                wasmparser::Payload::ComponentExportSection(reader) if depth == 1 => {
+                    for export in reader {
+                        exports.push(export?.name.0.to_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert!(imports.iter().any(|i| i == "fermyon:spin/variables@2.0.0"));
+        assert!(!imports.iter().any(|i| i == "fermyon:spin/config"));
+        assert_eq!(vec!["wasi:http/incoming-handler@0.2.0"], exports);
+
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+        let engine = Engine::new(&config)?;
+        let component = Component::new(&engine, component)?;
+
+        let WorldReport::Platform2_0_0(report) = spin_abi_conformance::test_world(
+            &component,
+            &engine,
+            TestConfig {
+                invocation_style: InvocationStyle::WasiHttp,
+                world: World::Platform2_0_0,
+            },
+        )
+        .await?
+        else {
+            unreachable!("the `fermyon:spin/platform@2.0.0` suite was requested");
+        };
+        // The module only implements these two tests
+        assert_eq!(Ok(()), report.inbound_http);
+        assert_eq!(Ok(()), report.variables);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wit_bindgen_versions() {
+        use crate::WitBindgenVersion;
+
+        for (version, expected) in [
+            ("0.2.0", WitBindgenVersion::Other("0.2.0".into())),
+            ("0.3.0", WitBindgenVersion::V0_3OrV0_4),
+            ("0.4.1", WitBindgenVersion::V0_3OrV0_4),
+            ("0.8.0", WitBindgenVersion::GreaterThanV0_4),
+            ("0.19.2", WitBindgenVersion::GreaterThanV0_4),
+            ("1.0.0", WitBindgenVersion::GreaterThanV0_4),
+            ("1.2", WitBindgenVersion::GreaterThanV0_4),
+            ("1", WitBindgenVersion::Other("1".into())),
+            ("1.0.0.0", WitBindgenVersion::Other("1.0.0.0".into())),
+        ] {
+            assert_eq!(
+                expected,
+                WitBindgenVersion::from_processors([("wit-bindgen-rust", version)]),
+                "{version}"
+            );
+        }
+
+        assert_eq!(
+            WitBindgenVersion::V0_2,
+            WitBindgenVersion::from_processors([("rustc", "1.76.0")])
+        );
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_02_report() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_02.wasm"
+        ))
+        .await?;
+
+        let (component, report) = crate::componentize_with_report(&module)?;
+
+        assert_eq!(
+            Some(crate::WitBindgenVersion::V0_2),
+            report.wit_bindgen_version
+        );
+        assert_eq!(crate::Abi::OldBindgen, report.abi);
+        assert_eq!(crate::Adapter::Spin, report.adapter);
+        assert!(report
+            .rewritten_imports
+            .iter()
+            .any(|import| import.module == "spin-config"
+                && import.new_module == "wasi_snapshot_preview1"
+                && import.new_name == "spin-config:get-config"));
+        let mut kept_exports = report.kept_exports.clone();
+        kept_exports.sort();
+        assert_eq!(vec!["inbound-http", "inbound-redis"], kept_exports);
+        assert!(report.dropped_exports.is_empty());
+        assert!(report.warnings.is_empty());
+        assert_eq!(module.len(), report.input_size);
+        assert_eq!(component.len(), report.output_size);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_08_report() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+
+        let (component, report) = crate::componentize_with_report(&module)?;
+
+        assert_eq!(
+            Some(crate::WitBindgenVersion::GreaterThanV0_4),
+            report.wit_bindgen_version
+        );
+        assert_eq!(crate::Abi::NewBindgen, report.abi);
+        assert_eq!(crate::Adapter::Preview1, report.adapter);
+        assert!(report.rewritten_imports.is_empty());
+        assert_eq!(component.len(), report.output_size);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_02_inspect() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        let info = crate::inspect_module(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_02.wasm"
+            ))
+            .await?,
+        )?;
+
+        assert_eq!(crate::WitBindgenVersion::V0_2, info.wit_bindgen_version);
+        assert_eq!(crate::Toolchain::Rust, info.toolchain);
+        assert!(info.spin_imports.iter().any(|i| i == "spin-config"));
+        let mut spin_exports = info.spin_exports.clone();
+        spin_exports.sort();
+        assert_eq!(
+            vec!["handle-http-request", "handle-redis-message"],
+            spin_exports
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_08_inspect() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let info = crate::inspect_module(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust_case_08.wasm"
+            ))
+            .await?,
+        )?;
+
+        assert_eq!(
+            crate::WitBindgenVersion::GreaterThanV0_4,
+            info.wit_bindgen_version
+        );
+        assert_eq!(crate::Toolchain::Rust, info.toolchain);
+        assert!(info
+            .producers
+            .processed_by
+            .iter()
+            .any(|p| p.name.starts_with("wit-bindgen")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_toolchains() -> Result<()> {
+        // A module with the given `producers` entries, exporting a function under each of the given names
+        let module = |producers: &[(&str, &str, &str)], exports: &[&str]| {
+            let mut fields = std::collections::BTreeMap::<_, wasm_encoder::ProducersField>::new();
+            for (field, name, version) in producers {
+                fields.entry(*field).or_default().value(name, version);
+            }
+            let mut section = wasm_encoder::ProducersSection::new();
+            for (field, values) in &fields {
+                section.field(field, values);
+            }
+
+            let mut types = wasm_encoder::TypeSection::new();
+            types.function([], []);
+            let mut functions = wasm_encoder::FunctionSection::new();
+            functions.function(0);
+            let mut export_section = wasm_encoder::ExportSection::new();
+            for name in exports {
+                export_section.export(name, wasm_encoder::ExportKind::Func, 0);
+            }
+            let mut code = wasm_encoder::CodeSection::new();
+            let mut function = wasm_encoder::Function::new([]);
+            function.instruction(&wasm_encoder::Instruction::End);
+            code.function(&function);
+
+            let mut module = wasm_encoder::Module::new();
+            module
+                .section(&types)
+                .section(&functions)
+                .section(&export_section)
+                .section(&code)
+                .section(&section);
+            module.finish()
+        };
+
+        for (producers, exports, expected) in [
+            (
+                &[("processed-by", "TinyGo", "0.30.0")][..],
+                &[][..],
+                crate::Toolchain::TinyGo,
+            ),
+            (&[("language", "Go", "1.21")], &[], crate::Toolchain::TinyGo),
+            // The language is whatever componentize-py embedded, so the tool should take precedence over it
+            (
+                &[
+                    ("language", "Python", "3.12"),
+                    ("processed-by", "componentize-py", "0.13.0"),
+                ],
+                &[],
+                crate::Toolchain::ComponentizePy,
+            ),
+            (
+                &[
+                    ("language", "JavaScript", ""),
+                    ("processed-by", "componentize-js", "0.8.0"),
+                ],
+                &[],
+                crate::Toolchain::ComponentizeJs,
+            ),
+            (
+                &[("processed-by", "rustc", "1.76.0")],
+                &[],
+                crate::Toolchain::Rust,
+            ),
+            (
+                &[("language", "C11", "")],
+                &[],
+                crate::Toolchain::Other("C11".into()),
+            ),
+            // TinyGo's scheduler gives it away even without a `producers` section
+            (
+                &[],
+                &["_start", "asyncify_start_unwind"],
+                crate::Toolchain::TinyGo,
+            ),
+            (&[], &["_start"], crate::Toolchain::Unknown),
+        ] {
+            assert_eq!(
+                expected,
+                crate::inspect_module(&module(producers, exports))?.toolchain,
+                "{producers:?} {exports:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn infer_abi() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        build_rust_test_case("rust-case-0.8");
+        build_rust_test_case("rust-command");
+
+        for (path, expected) in [
+            (
+                concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+                crate::Abi::OldBindgen,
+            ),
+            (
+                concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_08.wasm"),
+                crate::Abi::NewBindgen,
+            ),
+            (
+                concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust-command.wasm"),
+                crate::Abi::Command,
+            ),
+        ] {
+            assert_eq!(
+                Some(expected),
+                crate::infer_abi(&fs::read(path).await?)?,
+                "{path}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn infer_abi_v0_2_sqlite() -> Result<()> {
+        let module = |imports: &[(&str, &str)]| {
+            let mut types = wasm_encoder::TypeSection::new();
+            types.function([], []);
+            let mut section = wasm_encoder::ImportSection::new();
+            for (module, name) in imports {
+                section.import(module, name, wasm_encoder::EntityType::Function(0));
+            }
+            let mut module = wasm_encoder::Module::new();
+            module.section(&types).section(&section);
+            module.finish()
+        };
+
+        // Spin SDKs built with wit-bindgen 0.2 import `sqlite` too, so a producer-stripped module importing it
+        // alongside `spin-config` must still be taken for wit-bindgen 0.2
+        assert_eq!(
+            Some(crate::Abi::OldBindgen),
+            crate::infer_abi(&module(&[
+                ("spin-config", "get-config"),
+                ("sqlite", "open")
+            ]))?
+        );
+        assert_eq!(None, crate::infer_abi(&module(&[("sqlite", "open")]))?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rust_command_forced_abi() -> Result<()> {
+        build_rust_test_case("rust-command");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust-command.wasm"
+        ))
+        .await?;
+
+        let (_, report) = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::Command)
+            .componentize_with_report(&module)?;
+
+        assert_eq!(crate::Abi::Command, report.abi);
+        assert_eq!(crate::Adapter::Command, report.adapter);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_08_supplied_adapter() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+
+        let adapter = fs::read("adapters/wasi_snapshot_preview1.reactor.wasm").await?;
+        crate::validate_adapter(crate::Adapter::Preview1, &adapter)?;
+
+        let component = crate::ComponentizeOptions::new()
+            .adapter(crate::Adapter::Preview1, adapter)
+            .componentize(&module)?;
+        assert_eq!(crate::componentize(&module)?, component);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_adapter() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+
+        // A Spin module exports none of the functions an adapter must provide
+        let result = crate::ComponentizeOptions::new()
+            .adapter(crate::Adapter::Preview1, module.clone())
+            .componentize(&module);
+        assert!(matches!(
+            result,
+            Err(crate::ComponentizeError::InvalidAdapter { adapter: crate::Adapter::Preview1, missing_exports })
+                if missing_exports.iter().any(|e| e == "fd_write")
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wasi_release_candidate_embedded() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+
+        let options =
+            crate::ComponentizeOptions::new().expect_wasi_version(crate::WasiVersion::Rc2023_11_10);
+
+        // The embedded adapters only target WASI 0.2.0
+        assert!(matches!(
+            options.componentize(&module),
+            Err(crate::ComponentizeError::MissingAdapter(
+                crate::Adapter::Preview1
+            ))
+        ));
+
+        let adapter = fs::read("adapters/wasi_snapshot_preview1.reactor.wasm").await?;
+        assert!(matches!(
+            options.adapter(crate::Adapter::Preview1, adapter).componentize(&module),
+            Err(crate::ComponentizeError::WasiVersionMismatch { found, .. }) if found == "0.2.0"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn wasi_release_candidate_adapter() -> Result<()> {
+        // A stand-in for an adapter from wasmtime 15, whose `fd_write` reads the release candidate's clock
+        let adapter = fake_adapter(
+            &["fd_write"],
+            r#"
+            (import "wasi:clocks/monotonic-clock@0.2.0-rc-2023-11-10" "now" (func $now (result i64)))
+            (func (export "fd_write") (param i32 i32 i32 i32) (result i32)
+              call $now
+              drop
+              i32.const 0)
+            "#,
+            r#"
+            package wasi:clocks@0.2.0-rc-2023-11-10;
+
+            interface monotonic-clock {
+              now: func() -> u64;
+            }
+
+            world adapter {
+              import monotonic-clock;
+            }
+            "#,
+        )?;
+
+        let module = wat::parse_str(
+            r#"
+            (module
+              (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+            )
+            "#,
+        )?;
+        let options = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::NewBindgen)
+            .adapter(crate::Adapter::Preview1, adapter);
+
+        let component = options
+            .clone()
+            .expect_wasi_version(crate::WasiVersion::Rc2023_11_10)
+            .componentize(&module)?;
+        let mut imports = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&component) {
+            if let wasmparser::Payload::ComponentImportSection(reader) = payload? {
+                for import in reader {
+                    imports.push(import?.name.0.to_owned());
+                }
+            }
+        }
+        assert_eq!(
+            vec!["wasi:clocks/monotonic-clock@0.2.0-rc-2023-11-10"],
+            imports
+        );
+
+        assert!(matches!(
+            options.componentize(&module),
+            Err(crate::ComponentizeError::WasiVersionMismatch { found, .. })
+                if found == "0.2.0-rc-2023-11-10"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_registry() {
+        let registry = crate::ExportRegistry::new()
+            .register("handle-timer", "inbound-timer")
+            .register("handle-redis-message", "inbound-mqtt")
+            .register("handle-timer", "inbound-cron");
+
+        assert_eq!(Some("inbound-cron"), registry.interface("handle-timer"));
+        assert_eq!(
+            Some("inbound-mqtt"),
+            registry.interface("handle-redis-message")
+        );
+        assert_eq!(None, registry.interface("handle-http-request"));
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_02_registered_wit() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_02.wasm"
+        ))
+        .await?;
+
+        let result = crate::ComponentizeOptions::new()
+            .exports(crate::ExportRegistry::new().register_wit(
+                "handle-redis-message",
+                "inbound-mqtt",
+                "package example:mqtt; interface inbound-mqtt { handle-message: func(",
+            ))
+            .componentize(&module);
+        assert!(matches!(result, Err(crate::ComponentizeError::Metadata(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn registered_wit_export() -> Result<()> {
+        // A Spin adapter implementing a custom trigger by forwarding to the module's `handle-timer` export
+        let adapter = fake_adapter(
+            &["proc_exit"],
+            r#"
+            (import "__main_module__" "handle-timer" (func $handle-timer))
+            (func (export "proc_exit") (param i32) unreachable)
+            (func (export "example:timer/inbound-timer#handle-timer") call $handle-timer)
+            "#,
+            "package fermyon:spin; world reactor {}",
+        )?;
+
+        let module = wat::parse_str(
+            r#"
+            (module
+              (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+              (func (export "handle-timer"))
+            )
+            "#,
+        )?;
+        let (component, report) = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::OldBindgen)
+            .strict(true)
+            .adapter(crate::Adapter::Spin, adapter)
+            .exports(crate::ExportRegistry::new().register_wit(
+                "handle-timer",
+                "inbound-timer",
+                "package example:timer; interface inbound-timer { handle-timer: func(); }",
+            ))
+            .componentize_with_report(&module)?;
+
+        assert_eq!(vec!["inbound-timer"], report.kept_exports);
+        let mut exports = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&component) {
+            if let wasmparser::Payload::ComponentExportSection(reader) = payload? {
+                for export in reader {
+                    exports.push(export?.name.0.to_owned());
+                }
+            }
+        }
+        assert!(exports.iter().any(|e| e == "example:timer/inbound-timer"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn registered_unknown_interface() -> Result<()> {
+        // Without WIT of its own, the interface has to be one the embedded Spin adapter already exports
+        let module = wat::parse_str(r#"(module (func (export "handle-timer")))"#)?;
+        let result = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::OldBindgen)
+            .exports(crate::ExportRegistry::new().register("handle-timer", "inbound-timer"))
+            .componentize(&module);
+        assert!(matches!(
+            result,
+            Err(crate::ComponentizeError::UnknownInterface { export, interface })
+                if export == "handle-timer" && interface == "inbound-timer"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_diagnostics() {
+        let diagnostics = crate::ExportDiagnostics::new(
+            &[
+                "handle-http-reqest".to_owned(),
+                "handle-redis-message".to_owned(),
+                "canonical_abi_realloc".to_owned(),
+                "frobnicate".to_owned(),
+            ],
+            ["handle-redis-message", "handle-http-request"],
+        );
+
+        assert_eq!(
+            vec!["handle-http-reqest", "handle-redis-message", "frobnicate"],
+            diagnostics.found
+        );
+        assert_eq!(vec!["handle-redis-message"], diagnostics.matched);
+        assert_eq!(
+            vec![
+                crate::UnrecognizedExport {
+                    name: "handle-http-reqest".into(),
+                    closest: Some("handle-http-request".into()),
+                },
+                crate::UnrecognizedExport {
+                    name: "frobnicate".into(),
+                    closest: None,
+                },
+            ],
+            diagnostics.unrecognized
+        );
+    }
+
+    #[tokio::test]
+    async fn rust_wit_bindgen_02_strict() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_02.wasm"
+        ))
+        .await?;
+
+        crate::ComponentizeOptions::new()
+            .strict(true)
+            .componentize(&module)?;
+
+        // A misspelled trigger export has no mapping, so strict mode rejects the module
+        let module = wat::parse_str(r#"(module (func (export "handle-http-reqest")))"#)?;
+        let result = crate::ComponentizeOptions::new()
+            .abi(crate::Abi::OldBindgen)
+            .strict(true)
+            .componentize(&module);
+        assert!(matches!(
+            result,
+            Err(crate::ComponentizeError::UnrecognizedExports(diagnostics))
+                if diagnostics.unrecognized.iter().any(|e| e.name == "handle-http-reqest")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn gc_types_round_trip() -> Result<()> {
+        // The kind of module emitted by toolchains targeting typed function references and GC, e.g. Kotlin/Wasm
+        let module = wat::parse_str(
+            r#"
+            (module
+              (rec
+                (type $base (sub (func)))
+                (type $derived (sub $base (func))))
+              (type $point (struct (field i32) (field i32)))
+              (import "env" "callback" (global (ref null $derived)))
+              (import "env" "points" (table 1 (ref null $point)))
+              (import "env" "run" (func (type $base)))
+              (func (export "handle-http-request") (type $base))
+            )
+            "#,
+        )?;
+
+        let (retargeted, _, rewritten_imports) =
+            crate::retarget_imports_and_get_exports("wasi_snapshot_preview1", &module)?;
+        assert_eq!(3, rewritten_imports.len());
+
+        wasmparser::Validator::new_with_features(wasmparser::WasmFeatures {
+            function_references: true,
+            gc: true,
+            ..Default::default()
+        })
+        .validate_all(&retargeted)?;
+
+        // `TypeRef` has no `PartialEq`, so compare the types by their `Debug` output instead
+        let import_types = |module: &[u8]| -> Result<Vec<String>> {
+            let mut types = Vec::new();
+            for payload in wasmparser::Parser::new(0).parse_all(module) {
+                if let wasmparser::Payload::ImportSection(reader) = payload? {
+                    for import in reader {
+                        types.push(format!("{:?}", import?.ty));
+                    }
+                }
+            }
+            Ok(types)
+        };
+        assert_eq!(import_types(&module)?, import_types(&retargeted)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_construct_location() -> Result<()> {
+        // A heap type referring to a type by its index within a rec group can only be produced by hand, but must
+        // still be reported rather than crash the process
+        let import = wasmparser::Import {
+            module: "env",
+            name: "callback",
+            ty: wasmparser::TypeRef::Global(wasmparser::GlobalType {
+                content_type: wasmparser::ValType::Ref(
+                    wasmparser::RefType::new(
+                        true,
+                        wasmparser::HeapType::Concrete(wasmparser::UnpackedIndex::RecGroup(0)),
+                    )
+                    .unwrap(),
+                ),
+                mutable: false,
+            }),
+        };
+
+        match crate::entity_type(&import, 42) {
+            Err(crate::ComponentizeError::Unsupported {
+                section,
+                item,
+                offset,
+                ..
+            }) => {
+                assert_eq!("import", section);
+                assert_eq!("env::callback", item);
+                assert_eq!(42, offset);
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Feed truncated and randomly mutated modules through the functions which rewrite modules, which must reject
+    /// bad input with an error rather than a panic
+    #[test]
+    fn fuzz_module_rewriting() -> Result<()> {
+        let seeds = [
+            wat::parse_str(
+                r#"
+                (module
+                  (rec
+                    (type $base (sub (func)))
+                    (type $derived (sub $base (func))))
+                  (import "env" "callback" (global (ref null $derived)))
+                  (import "env" "points" (table 1 funcref))
+                  (import "spin-config" "get-config" (func (type $base)))
+                  (memory (export "memory") 1)
+                  (func (export "handle-http-request") (type $base))
+                  (func (export "inbound-http#handle-request") (type $base))
+                )
+                "#,
+            )?,
+            wat::parse_str(
+                r#"
+                (module
+                  (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                  (import "env" "tag" (tag (param i32)))
+                  (func (export "_start"))
+                  (@custom "component-type:reactor" "not a component")
+                )
+                "#,
+            )?,
+        ];
+
+        let interfaces = [("inbound-http", "fermyon:spin/inbound-http")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+
+        let check = |module: &[u8]| {
+            _ = crate::retarget_imports_and_get_exports("wasi_snapshot_preview1", module);
+            _ = crate::qualify_interface_names(&interfaces, module);
+            _ = crate::add_custom_section("component-type:fuzz", module, module);
+        };
+
+        // A small xorshift generator keeps the test deterministic without extra dependencies
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for seed in &seeds {
+            for len in 0..seed.len() {
+                check(&seed[..len]);
+            }
+
+            for _ in 0..5000 {
+                let mut module = seed.clone();
+                for _ in 0..(next() % 4 + 1) {
+                    let index = (next() % module.len() as u64) as usize;
+                    match next() % 3 {
+                        0 => module[index] = next() as u8,
+                        1 => module.insert(index, next() as u8),
+                        _ => _ = module.remove(index),
+                    }
+                }
+                check(&module);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn debug_info() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        build_rust_test_case("rust-case-0.8");
+
+        let custom_section_names = |binary: &[u8]| -> Result<Vec<String>> {
+            let mut names = Vec::new();
+            for payload in wasmparser::Parser::new(0).parse_all(binary) {
+                if let wasmparser::Payload::CustomSection(section) = payload? {
+                    names.push(section.name().to_owned());
+                }
+            }
+            Ok(names)
+        };
+
+        for path in [
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_08.wasm"),
+        ] {
+            let module = fs::read(path).await?;
+            assert!(custom_section_names(&module)?.iter().any(|n| n == "name"));
+
+            let (preserved, _) = crate::ComponentizeOptions::new()
+                .debug_info(crate::DebugInfo::Preserve)
+                .componentize_with_report(&module)?;
+
+            let (stripped, report) = crate::ComponentizeOptions::new()
+                .debug_info(crate::DebugInfo::Strip)
+                .componentize_with_report(&module)?;
+            assert_eq!(module.len(), report.input_size);
+            assert!(stripped.len() < preserved.len(), "{path}");
+            assert!(
+                !custom_section_names(&stripped)?
+                    .iter()
+                    .any(|n| n == "name" || n.starts_with(".debug_")),
+                "{path}"
+            );
+
+            // Stripping changes the module, so there is nothing left to preserve
+            assert!(matches!(
+                crate::debug_info::verify(&module, &stripped),
+                Err(crate::ComponentizeError::DebugInfo(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn componentize_stream() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+
+        let mut component = Vec::new();
+        let report = crate::componentize_stream(module.as_slice(), &mut component)?;
+        assert_eq!(crate::componentize(&module)?, component);
+        assert_eq!(Some(component.len()), report.map(|r| r.output_size));
+
+        let mut copy = Vec::new();
+        assert!(crate::componentize_stream(component.as_slice(), &mut copy)?.is_none());
+        assert_eq!(component, copy);
+
+        assert!(matches!(
+            crate::componentize_stream(&b"\0asm"[..], Vec::new()),
+            Err(crate::ComponentizeError::UnknownEncoding)
+        ));
+
+        /// Hands out a few bytes per read, so that sections arrive in pieces
+        struct Trickle<'a>(&'a [u8]);
+
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = buf.len().min(7);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        // A wit-bindgen 0.2 module, whose imports are moved to the adapter, with sections larger than a single read
+        let module = wat::parse_str(format!(
+            r#"
+            (module
+              (import "spin-config" "get-config" (func (param i32 i32 i32)))
+              (memory (export "memory") 4)
+              (data (i32.const 0) "{}")
+              (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+                i32.const 0)
+              (func (export "canonical_abi_free") (param i32 i32 i32))
+              (func (export "handle-http-request")
+                (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)
+                i32.const 0)
+              (@custom ".debug_info" "{}")
+            )
+            "#,
+            "lorem ipsum ".repeat(20_000),
+            "dolor sit amet ".repeat(10_000),
+        ))?;
+        for debug_info in [
+            crate::DebugInfo::Keep,
+            crate::DebugInfo::Preserve,
+            crate::DebugInfo::Strip,
+        ] {
+            let options = crate::ComponentizeOptions::new().debug_info(debug_info);
+            let expected = options.componentize_with_report(&module)?;
+            let mut component = Vec::new();
+            let report = options.componentize_stream(Trickle(&module), &mut component)?;
+            assert_eq!(Some(expected), report.map(|report| (component, report)));
+        }
+
+        assert!(matches!(
+            crate::componentize_stream(&module[..module.len() / 2], Vec::new()),
+            Err(crate::ComponentizeError::InvalidBinary(_))
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn cache() -> Result<()> {
+        build_rust_test_case("rust-case-0.8");
+        let module = fs::read(concat!(
+            env!("OUT_DIR"),
+            "/wasm32-wasi/release/rust_case_08.wasm"
+        ))
+        .await?;
+        let expected = crate::componentize(&module)?;
+
+        let dir = PathBuf::from(concat!(env!("OUT_DIR"), "/cache-test"));
+        _ = std::fs::remove_dir_all(&dir);
+        let cache = crate::Cache::new(&dir)?;
+        let options = crate::ComponentizeOptions::new();
+
+        let entries = || -> Result<Vec<PathBuf>> {
+            Ok(std::fs::read_dir(&dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?)
+        };
+
+        assert_eq!(
+            expected,
+            &*cache.componentize_if_necessary(&options, &module)?
+        );
+        let initial = entries()?;
+        let [entry] = &initial[..] else {
+            panic!("expected one cache entry");
+        };
+
+        // A hit returns the stored component, and a corrupted entry is replaced
+        assert_eq!(
+            expected,
+            &*cache.componentize_if_necessary(&options, &module)?
+        );
+        let mut corrupted = std::fs::read(entry)?;
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        std::fs::write(entry, corrupted)?;
+        assert_eq!(
+            expected,
+            &*cache.componentize_if_necessary(&options, &module)?
+        );
+        assert_eq!(vec![entry.clone()], entries()?);
+
+        // Different options get a different entry, and eviction keeps the cache within bounds.  The new entry
+        // survives even when the old one looks more recently used, as it may with coarse timestamps.
+        std::fs::File::options()
+            .append(true)
+            .open(entry)?
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))?;
+        let cache = cache.max_size(expected.len() as u64 * 3 / 2);
+        cache.componentize_if_necessary(&options.clone().strict(true), &module)?;
+        let remaining = entries()?;
+        assert_eq!(1, remaining.len());
+        assert_ne!(entry, &remaining[0]);
+
+        // Components are not cached
+        assert!(matches!(
+            cache.componentize_if_necessary(&options, &expected)?,
+            std::borrow::Cow::Borrowed(_)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deterministic_output() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        build_rust_test_case("rust-case-0.4");
+        build_rust_test_case("rust-case-0.8");
+        build_rust_test_case("rust-command");
+
+        for path in [
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_04.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_08.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust-command.wasm"),
+        ] {
+            let module = std::sync::Arc::new(fs::read(path).await?);
+            let component = crate::componentize(&module)?;
+
+            // Each thread gets differently seeded hash maps, which would expose any dependence on their order
+            let threads = (0..4)
+                .map(|_| {
+                    let module = module.clone();
+                    std::thread::spawn(move || crate::componentize(&module))
+                })
+                .collect::<Vec<_>>();
+            for thread in threads {
+                assert!(thread.join().unwrap()? == component, "{path}");
+            }
+
+            crate::verify(&module, &component)?;
+
+            let mut tampered = component.clone();
+            let last = tampered.len() - 1;
+            tampered[last] ^= 0xff;
+            assert!(matches!(
+                crate::verify(&module, &tampered),
+                Err(crate::ComponentizeError::VerificationFailed { offset, .. }) if offset == last
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn componentize_batch() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        build_rust_test_case("rust-case-0.4");
+        build_rust_test_case("rust-case-0.8");
+        build_rust_test_case("rust-command");
+
+        let mut modules = Vec::new();
+        for path in [
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_04.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_08.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust-command.wasm"),
+        ] {
+            modules.push(fs::read(path).await?);
+        }
+        modules.push(b"not a wasm binary".to_vec());
+        // Several old-style modules at once exercise sharing the decoded Spin adapter
+        modules.extend(modules[..2].to_vec());
+
+        let results = crate::ComponentizeOptions::new()
+            .componentize_batch(&modules, std::num::NonZeroUsize::new(3));
+        assert_eq!(modules.len(), results.len());
+
+        for (module, batch) in modules.iter().zip(results) {
+            match (crate::componentize_with_report(module), batch.result) {
+                (Ok(expected), Ok(actual)) => assert!(expected == actual),
+                (Err(crate::ComponentizeError::InvalidBinary(_)), Err(e)) => {
+                    assert!(matches!(e, crate::ComponentizeError::InvalidBinary(_)))
+                }
+                (expected, actual) => panic!(
+                    "expected {:?}, got {:?}",
+                    expected.map(|_| ()),
+                    actual.map(|_| ())
+                ),
+            }
+        }
+
+        assert!(crate::ComponentizeOptions::new()
+            .componentize_batch::<Vec<u8>>(&[], None)
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn componentizer() -> Result<()> {
+        build_rust_test_case("rust-case-0.2");
+        build_rust_test_case("rust-case-0.4");
+
+        let componentizer = crate::Componentizer::new(crate::ComponentizeOptions::new());
+        // Repeating a module reuses the world prepared for it
+        for path in [
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_04.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_02.wasm"),
+            concat!(env!("OUT_DIR"), "/wasm32-wasi/release/rust_case_04.wasm"),
+        ] {
+            let module = fs::read(path).await?;
+            assert!(
+                componentizer.componentize_with_report(&module)?
+                    == crate::componentize_with_report(&module)?,
+                "{path}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn go() -> Result<()> {
+        let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+        let mut cmd = process::Command::new("tinygo");
+        cmd.arg("build")
+            .current_dir("tests/go-case")
+            .arg("-target=wasi")
+            .arg("-gc=leaking")
+            .arg("-no-debug")
+            .arg("-o")
+            .arg(out_dir.join("go_case.wasm"))
+            .arg("main.go");
+
+        // If just skip this if TinyGo is not installed
+        _ = cmd.status();
+        run_spin(&fs::read(concat!(env!("OUT_DIR"), "/go_case.wasm")).await?).await
+    }
+
+    #[tokio::test]
+    async fn rust_command() -> Result<()> {
+        build_rust_test_case("rust-command");
+        run_command(
+            &fs::read(concat!(
+                env!("OUT_DIR"),
+                "/wasm32-wasi/release/rust-command.wasm"
+            ))
+            .await?,
+        )
+        .await
+    }
+
+    #[test]
+    fn invalid_binary() {
+        assert!(matches!(
+            crate::componentize(b"not a wasm binary"),
+            Err(crate::ComponentizeError::InvalidBinary(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_code_section() -> Result<()> {
+        // wasmparser reports the code section before reading it, so its range runs past the end of the module
+        let mut module = wat::parse_str(r#"(module (func (export "handle-http-request")))"#)?;
+        module.pop();
+
+        assert!(matches!(
+            crate::inspect_module(&module),
+            Err(crate::ComponentizeError::InvalidBinary(_))
+        ));
+        assert!(matches!(
+            crate::componentize(&module),
+            Err(crate::ComponentizeError::InvalidBinary(_))
+        ));
+        assert!(matches!(
+            crate::validate_adapter(crate::Adapter::Preview1, &module),
+            Err(crate::ComponentizeError::InvalidBinary(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn component_passes_through() -> Result<()> {
+        let component = wasm_encoder::Component::new().finish();
+        assert!(matches!(
+            crate::componentize_if_necessary(&component)?,
+            std::borrow::Cow::Borrowed(_)
+        ));
+        Ok(())
+    }
+
+    /// Build an adapter from `items`, which define the `implemented` preview1 functions, leaving every other
+    /// preview1 function unimplemented, and embed the world of the WIT package `wit` as its component metadata
+    fn fake_adapter(implemented: &[&str], items: &str, wit: &str) -> Result<Vec<u8>> {
+        let exports = crate::adapters::PREVIEW1_FUNCTIONS
+            .iter()
+            .filter(|name| !implemented.contains(name))
+            .map(|name| format!(r#"(export "{name}" (func $unimplemented))"#))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut adapter = wat::parse_str(format!(
+            r#"
+            (module
+              {items}
+              (func $unimplemented unreachable)
+              {exports}
+            )
+            "#
+        ))?;
+        let mut resolve = wit_parser::Resolve::default();
+        let package = resolve.push(wit_parser::UnresolvedPackage::parse(
+            "adapter.wit".as_ref(),
+            wit,
+        )?)?;
+        let world = resolve.select_world(package, None)?;
+        wit_component::embed_component_metadata(
+            &mut adapter,
+            &resolve,
+            world,
+            wit_component::StringEncoding::UTF8,
+        )?;
+        Ok(adapter)
+    }
+
     fn build_rust_test_case(name: &str) {
         let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
         let mut cmd = process::Command::new("cargo");
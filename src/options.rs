@@ -0,0 +1,476 @@
+use {
+    crate::{
+        adapters::{check_wasi_version, required_adapter, DecodedSpinAdapter},
+        componentizer::{BatchResult, Shared},
+        debug_info::{self, DebugInfo},
+        Adapter, ComponentizeError, ComponentizeReport, Componentizer, ExportRegistry, Result,
+        ScannedModule, SpinWorld, WasiVersion, WitBindgenVersion, EXPORT_INTERFACES,
+        V0_2_IMPORT_MODULES, V0_4_IMPORT_MODULES,
+    },
+    serde::Serialize,
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        io::{self, Read, Write},
+        num::NonZeroUsize,
+        sync::Arc,
+    },
+    wasmparser::{Encoding, Parser, Payload},
+};
+
+/// The bindgen ABI a module was built against, which determines how it is componentized
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Abi {
+    /// wit-bindgen 0.2, adapted by [`crate::componentize_old_bindgen`]
+    OldBindgen,
+    /// wit-bindgen 0.3 and 0.4, adapted by [`crate::componentize_v0_4_bindgen`]
+    V0_4Bindgen,
+    /// wit-bindgen 0.5 and newer, adapted by [`crate::componentize_new_bindgen`]
+    NewBindgen,
+    /// A `wasi:cli/run` command, adapted by [`crate::componentize_command`]
+    Command,
+}
+
+/// Options for converting a module to a component
+///
+/// By default the ABI is detected from the module's `producers` metadata.  When that metadata does not name a
+/// wit-bindgen version (e.g. because it was stripped by `wasm-opt --strip-producers`), the ABI is inferred from the
+/// names of the module's imports and exports instead.
+///
+/// Adapters default to those embedded in this crate (see [`crate::embedded_adapter`]), and may be replaced with
+/// [`ComponentizeOptions::adapter`].  The component imports whichever WASI version its adapters do, which is 0.2.0 for
+/// the embedded ones.
+#[derive(Debug, Clone)]
+pub struct ComponentizeOptions {
+    abi: Option<Abi>,
+    infer_abi: bool,
+    wasi_version: WasiVersion,
+    spin_world: SpinWorld,
+    exports: ExportRegistry,
+    strict: bool,
+    debug_info: DebugInfo,
+    adapters: HashMap<Adapter, Vec<u8>>,
+}
+
+impl Default for ComponentizeOptions {
+    fn default() -> Self {
+        Self {
+            abi: None,
+            infer_abi: true,
+            wasi_version: WasiVersion::default(),
+            spin_world: SpinWorld::default(),
+            exports: ExportRegistry::default(),
+            strict: false,
+            debug_info: DebugInfo::default(),
+            adapters: HashMap::new(),
+        }
+    }
+}
+
+impl ComponentizeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the specified ABI rather than detecting it
+    pub fn abi(mut self, abi: Abi) -> Self {
+        self.abi = Some(abi);
+        self
+    }
+
+    /// Whether to infer the ABI from import and export names when the `producers` metadata does not name a
+    /// wit-bindgen version (enabled by default)
+    ///
+    /// When disabled, such modules are assumed to have been built with wit-bindgen 0.2.
+    pub fn infer_abi(mut self, infer_abi: bool) -> Self {
+        self.infer_abi = infer_abi;
+        self
+    }
+
+    /// The WASI version every adapter must import (WASI 0.2.0 by default)
+    ///
+    /// This only validates the adapters and does not change what they import, so expecting one of the release
+    /// candidates requires supplying adapters which import it; the embedded adapters are rejected with
+    /// [`crate::ComponentizeError::MissingAdapter`].
+    pub fn expect_wasi_version(mut self, wasi_version: WasiVersion) -> Self {
+        self.wasi_version = wasi_version;
+        self
+    }
+
+    /// The Spin world to componentize wit-bindgen 0.2 modules against (the unversioned `reactor` world by default)
+    ///
+    /// Modules built with newer wit-bindgen versions already target a world of their own choosing, so this has no
+    /// effect on them.
+    pub fn spin_world(mut self, spin_world: SpinWorld) -> Self {
+        self.spin_world = spin_world;
+        self
+    }
+
+    /// Additional mappings from the exports of wit-bindgen 0.2 modules to interfaces of the Spin world
+    pub fn exports(mut self, exports: ExportRegistry) -> Self {
+        self.exports = exports;
+        self
+    }
+
+    /// Whether to fail when a wit-bindgen 0.2 module has exports which do not map to any interface (disabled by
+    /// default)
+    ///
+    /// Such exports are otherwise dropped and listed in [`ComponentizeReport::warnings`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// What to do with debug information and `producers` metadata ([`DebugInfo::Keep`] by default)
+    pub fn debug_info(mut self, debug_info: DebugInfo) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    /// Use the given adapter bytes in place of the embedded adapter of the same kind
+    ///
+    /// The adapter is checked with [`crate::validate_adapter`] when it is first needed, along with the WASI version
+    /// it imports.
+    pub fn adapter(mut self, adapter: Adapter, bytes: impl Into<Vec<u8>>) -> Self {
+        self.adapters.insert(adapter, bytes.into());
+        self
+    }
+
+    /// The caller-supplied adapter of the given kind, falling back to the embedded one
+    fn adapter_bytes(&self, adapter: Adapter) -> Result<Cow<[u8]>> {
+        let bytes = match self.adapters.get(&adapter) {
+            Some(bytes) => {
+                crate::validate_adapter(adapter, bytes)?;
+                check_wasi_version(adapter, bytes, self.wasi_version)?;
+                bytes
+            }
+            None => required_adapter(adapter, self.wasi_version)?,
+        };
+
+        match self.debug_info {
+            DebugInfo::Strip => debug_info::strip(bytes),
+            DebugInfo::Keep | DebugInfo::Preserve => Ok(Cow::Borrowed(bytes)),
+        }
+    }
+
+    /// The Spin adapter decoded for an earlier module, or decoded now if this is the first
+    fn spin_adapter(&self, shared: &Shared) -> Result<Arc<DecodedSpinAdapter>> {
+        // Holding the lock while decoding makes any other modules wait for the result rather than decode it again
+        let mut spin = shared.spin.lock().unwrap();
+        if let Some(spin) = &*spin {
+            return Ok(spin.clone());
+        }
+
+        let decoded = Arc::new(DecodedSpinAdapter::decode(
+            &self.adapter_bytes(Adapter::Spin)?,
+        )?);
+        *spin = Some(decoded.clone());
+        Ok(decoded)
+    }
+
+    pub fn componentize(&self, module: &[u8]) -> Result<Vec<u8>> {
+        self.componentize_with_report(module)
+            .map(|(component, _)| component)
+    }
+
+    /// Like [`ComponentizeOptions::componentize`], but also returns a [`ComponentizeReport`] describing how the
+    /// module was converted
+    pub fn componentize_with_report(&self, module: &[u8]) -> Result<(Vec<u8>, ComponentizeReport)> {
+        self.componentize_shared(module, &Shared::default())
+    }
+
+    /// Componentize each of `modules` on a pool of `threads` threads (one per available CPU by default), returning
+    /// the results in the same order as the modules
+    ///
+    /// The adapters are prepared once for the whole batch rather than once per module.
+    pub fn componentize_batch<M: AsRef<[u8]> + Sync>(
+        &self,
+        modules: &[M],
+        threads: Option<NonZeroUsize>,
+    ) -> Vec<BatchResult> {
+        Componentizer::new(self.clone()).componentize_batch(modules, threads)
+    }
+
+    pub(crate) fn componentize_shared(
+        &self,
+        module: &[u8],
+        shared: &Shared,
+    ) -> Result<(Vec<u8>, ComponentizeReport)> {
+        let (abi, version) = self.detect_abi(module)?;
+
+        // The `producers` section has already served its purpose in detecting the ABI
+        let input = match self.debug_info {
+            DebugInfo::Strip => debug_info::strip(module)?,
+            DebugInfo::Keep | DebugInfo::Preserve => Cow::Borrowed(module),
+        };
+
+        let (component, mut report) = self.componentize_as(abi, &input, shared)?;
+        report.wit_bindgen_version = version;
+        report.input_size = module.len();
+
+        if self.debug_info == DebugInfo::Preserve {
+            debug_info::verify(module, &component)?;
+        }
+
+        Ok((component, report))
+    }
+
+    /// The ABI to componentize `module` as, along with the wit-bindgen version its metadata records
+    ///
+    /// Only the imports, exports and `component-type` sections of `module` are consulted.
+    fn detect_abi(&self, module: &[u8]) -> Result<(Abi, Option<WitBindgenVersion>)> {
+        Ok(match self.abi {
+            // The metadata may well be missing or unreadable, which is why the caller is overriding the ABI
+            Some(abi) => (abi, WitBindgenVersion::from_module(module).ok()),
+            None => {
+                let version = WitBindgenVersion::from_module(module)?;
+                let abi = match &version {
+                    WitBindgenVersion::V0_2 if self.infer_abi => {
+                        infer_abi(module)?.unwrap_or(Abi::OldBindgen)
+                    }
+                    WitBindgenVersion::V0_2 => Abi::OldBindgen,
+                    WitBindgenVersion::V0_3OrV0_4 => Abi::V0_4Bindgen,
+                    WitBindgenVersion::GreaterThanV0_4 => Abi::NewBindgen,
+                    WitBindgenVersion::Other(other) => {
+                        return Err(ComponentizeError::UnsupportedBindgenVersion(other.clone()))
+                    }
+                };
+                (abi, Some(version))
+            }
+        })
+    }
+
+    /// Componentize `input`, which has already had any debug information stripped, as `abi`
+    fn componentize_as(
+        &self,
+        abi: Abi,
+        input: &[u8],
+        shared: &Shared,
+    ) -> Result<(Vec<u8>, ComponentizeReport)> {
+        match abi {
+            Abi::OldBindgen => crate::componentize_old_bindgen_with_report(
+                input,
+                &*self.spin_adapter(shared)?,
+                self.spin_world,
+                &self.exports,
+                self.strict,
+            ),
+            Abi::V0_4Bindgen => crate::componentize_v0_4_bindgen_with_report(
+                input,
+                &*self.spin_adapter(shared)?,
+                &self.adapter_bytes(Adapter::Preview1)?,
+            ),
+            Abi::NewBindgen => crate::componentize_new_bindgen_with_report(
+                input,
+                &self.adapter_bytes(Adapter::Preview1)?,
+            ),
+            Abi::Command => crate::componentize_command_with_report(
+                input,
+                &self.adapter_bytes(Adapter::Command)?,
+            ),
+        }
+    }
+
+    /// Like [`crate::verify`], but componentizing with these options
+    pub fn verify(&self, module: &[u8], component: &[u8]) -> Result<()> {
+        let expected = self.componentize(module)?;
+        if expected == component {
+            return Ok(());
+        }
+
+        let offset = expected
+            .iter()
+            .zip(component)
+            .position(|(expected, actual)| expected != actual)
+            .unwrap_or(expected.len().min(component.len()));
+        Err(ComponentizeError::VerificationFailed {
+            offset,
+            expected_size: expected.len(),
+            actual_size: component.len(),
+        })
+    }
+
+    /// Feed everything besides the module which determines the component produced into `hash`
+    #[cfg(feature = "cache")]
+    pub(crate) fn hash(&self, hash: &mut dyn FnMut(&[u8])) {
+        hash(
+            format!(
+                "{:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+                self.abi,
+                self.infer_abi,
+                self.wasi_version,
+                self.spin_world,
+                self.exports,
+                self.strict,
+                self.debug_info
+            )
+            .as_bytes(),
+        );
+        for adapter in [Adapter::Spin, Adapter::Preview1, Adapter::Command] {
+            hash(
+                self.adapters
+                    .get(&adapter)
+                    .map(Vec::as_slice)
+                    .or_else(|| crate::embedded_adapter(adapter))
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    /// Like [`ComponentizeOptions::componentize_with_report`], but writes the component to `output`
+    ///
+    /// Passing a memory-mapped file avoids reading the module onto the heap, although the conversion itself still
+    /// makes its own copies.
+    pub fn componentize_to(
+        &self,
+        module: &[u8],
+        mut output: impl Write,
+    ) -> Result<ComponentizeReport> {
+        let (component, report) = self.componentize_with_report(module)?;
+        output.write_all(&component)?;
+        output.flush()?;
+        Ok(report)
+    }
+
+    /// Componentize the module read from `input`, writing the component to `output`
+    ///
+    /// Components are copied through unchanged without buffering them, in which case no report is returned.
+    /// Modules are parsed section by section as they are read, and that one pass finds everything which
+    /// [`ComponentizeOptions::componentize_to`] would otherwise parse the module again for, so the module is held
+    /// in memory once rather than copied for each step.  The result is identical to that of
+    /// [`ComponentizeOptions::componentize_to`].
+    pub fn componentize_stream(
+        &self,
+        mut input: impl Read,
+        mut output: impl Write,
+    ) -> Result<Option<ComponentizeReport>> {
+        // The preamble is the magic number followed by the version and layer, which tells modules and components
+        // apart
+        let mut preamble = [0; 8];
+        input.read_exact(&mut preamble).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                ComponentizeError::UnknownEncoding
+            } else {
+                e.into()
+            }
+        })?;
+
+        let encoding = match Parser::new(0).parse_all(&preamble).next() {
+            Some(Ok(Payload::Version { encoding, .. })) => encoding,
+            Some(Err(e)) => return Err(e.into()),
+            _ => return Err(ComponentizeError::UnknownEncoding),
+        };
+
+        match encoding {
+            Encoding::Component => {
+                output.write_all(&preamble)?;
+                io::copy(&mut input, &mut output)?;
+                output.flush()?;
+                Ok(None)
+            }
+            Encoding::Module => {
+                let (component, report) = self.componentize_scanned(&preamble, input)?;
+                output.write_all(&component)?;
+                output.flush()?;
+                Ok(Some(report))
+            }
+        }
+    }
+
+    /// Componentize the rest of a module following `preamble` in `input`, parsing each of its sections only once
+    /// while reading it
+    ///
+    /// Debug information is stripped as it is read, and only the import section of a wit-bindgen 0.2 module is
+    /// encoded again.  wit-component still parses the module once more while encoding the component.
+    fn componentize_scanned(
+        &self,
+        preamble: &[u8],
+        input: impl Read,
+    ) -> Result<(Vec<u8>, ComponentizeReport)> {
+        let strip = self.debug_info == DebugInfo::Strip;
+        let mut scanned = ScannedModule::read(preamble, input, |name| {
+            strip && debug_info::is_stripped(name)
+        })?;
+        let (abi, version) = self.detect_abi(&scanned.summary)?;
+
+        let shared = Shared::default();
+        let (component, mut report) = match abi {
+            Abi::OldBindgen => {
+                let rewritten_imports = scanned.retarget_imports(crate::ADAPTER_NAME)?;
+                crate::componentize_retargeted_old_bindgen(
+                    &scanned.module,
+                    &scanned.exports,
+                    rewritten_imports,
+                    &*self.spin_adapter(&shared)?,
+                    self.spin_world,
+                    &self.exports,
+                    self.strict,
+                )?
+            }
+            abi => self.componentize_as(abi, &scanned.module, &shared)?,
+        };
+        report.wit_bindgen_version = version;
+        report.input_size = scanned.input_size;
+
+        // Moving the imports leaves the code and debug sections as they were read
+        if self.debug_info == DebugInfo::Preserve {
+            debug_info::verify(&scanned.module, &component)?;
+        }
+
+        Ok((component, report))
+    }
+}
+
+/// Infer the ABI of a module from the names of its imports and exports, returning `None` if nothing in the module
+/// identifies it
+pub fn infer_abi(module: &[u8]) -> Result<Option<Abi>> {
+    let mut old_bindgen = false;
+    let mut v0_4_bindgen = false;
+    let mut new_bindgen = false;
+    let mut command = false;
+
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    old_bindgen |= V0_2_IMPORT_MODULES.contains(&import.module);
+                    v0_4_bindgen |= V0_4_IMPORT_MODULES.contains(&import.module);
+                    new_bindgen |= import.module.contains(':');
+                }
+            }
+
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    old_bindgen |= EXPORT_INTERFACES.iter().any(|(k, _)| *k == export.name);
+                    if let Some((interface, _)) = export.name.split_once('#') {
+                        if interface.contains(':') {
+                            new_bindgen = true;
+                        } else {
+                            v0_4_bindgen |= EXPORT_INTERFACES.iter().any(|(_, v)| *v == interface);
+                        }
+                    }
+                    command |= export.name == "_start";
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    // Fully qualified names are the most specific evidence, and the bare names of wit-bindgen 0.4 are more
+    // specific than those of 0.2
+    Ok(if new_bindgen {
+        Some(Abi::NewBindgen)
+    } else if v0_4_bindgen {
+        Some(Abi::V0_4Bindgen)
+    } else if old_bindgen {
+        Some(Abi::OldBindgen)
+    } else if command {
+        Some(Abi::Command)
+    } else {
+        None
+    })
+}
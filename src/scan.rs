@@ -0,0 +1,139 @@
+use {
+    crate::{retarget_imports, ComponentizeError, Result, RewrittenImport},
+    std::{io::Read, ops::Range},
+    wasm_encoder::{Encode, Module, RawSection, Section, SectionId},
+    wasmparser::{Chunk, Encoding, ExternalKind, ImportSectionReader, Parser, Payload},
+};
+
+/// The least to read from the input at a time, so that many small sections do not each take a read of their own
+const READ_SIZE: u64 = 64 * 1024;
+
+/// A core module read in a single pass, along with what componentizing it needs to know about it
+pub(crate) struct ScannedModule {
+    /// The module, less any sections left out while reading it
+    pub(crate) module: Vec<u8>,
+    /// A module consisting of only the import, export and `component-type` sections of `module`, which is all that
+    /// detecting its ABI looks at
+    pub(crate) summary: Vec<u8>,
+    /// The names of the functions `module` exports
+    pub(crate) exports: Vec<String>,
+    /// How many bytes were read, including any sections left out
+    pub(crate) input_size: usize,
+    /// The import section within `module`, along with the range of its contents in the input for error offsets
+    imports: Option<(Range<usize>, Range<usize>)>,
+}
+
+impl ScannedModule {
+    /// Read a core module from `input`, which follows the `preamble` already read from the same stream, parsing each
+    /// section once as soon as it has been read in full
+    ///
+    /// Custom sections whose name `leave_out` accepts are dropped as they are read.
+    pub(crate) fn read(
+        preamble: &[u8],
+        mut input: impl Read,
+        leave_out: impl Fn(&str) -> bool,
+    ) -> Result<Self> {
+        let mut parser = Parser::new(0);
+        let mut module = preamble.to_vec();
+        let mut summary = Module::new();
+        let mut exports = Vec::new();
+        let mut imports = None;
+        let mut input_size = module.len();
+        // Everything in `module` before this offset has been parsed
+        let mut parsed = 0;
+        let mut eof = false;
+
+        loop {
+            let (consumed, payload) = match parser.parse(&module[parsed..], eof)? {
+                Chunk::NeedMoreData(hint) => {
+                    let read = input
+                        .by_ref()
+                        .take(hint.max(READ_SIZE))
+                        .read_to_end(&mut module)?;
+                    input_size += read;
+                    eof = read == 0;
+                    continue;
+                }
+                Chunk::Parsed { consumed, payload } => (consumed, payload),
+            };
+            let start = parsed;
+            parsed += consumed;
+            // The contents of the section just parsed, which are all it consumed besides its id and size
+            let contents = |range: Range<usize>| &module[parsed - range.len()..parsed];
+
+            let mut left_out = false;
+            match payload {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => return Err(ComponentizeError::UnknownEncoding),
+                Payload::ImportSection(reader) => {
+                    summary.section(&RawSection {
+                        id: SectionId::Import as u8,
+                        data: contents(reader.range()),
+                    });
+                    imports = Some((start..parsed, reader.range()));
+                }
+                Payload::ExportSection(reader) => {
+                    summary.section(&RawSection {
+                        id: SectionId::Export as u8,
+                        data: contents(reader.range()),
+                    });
+                    for export in reader {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            exports.push(export.name.to_owned());
+                        }
+                    }
+                }
+                Payload::CustomSection(section) if leave_out(section.name()) => left_out = true,
+                Payload::CustomSection(section) if section.name().starts_with("component-type") => {
+                    summary.section(&RawSection {
+                        id: SectionId::Custom as u8,
+                        data: contents(section.range()),
+                    });
+                }
+                Payload::End(_) => break,
+                _ => (),
+            }
+
+            if left_out {
+                // Only the unparsed remainder of the last read follows the section, so this moves little
+                module.drain(start..parsed);
+                parsed = start;
+            }
+        }
+
+        Ok(Self {
+            module,
+            summary: summary.finish(),
+            exports,
+            input_size,
+            imports,
+        })
+    }
+
+    /// Move every import of the module to the `target` module as [`retarget_imports`] does, returning the imports
+    /// which were moved
+    ///
+    /// Only the import section is encoded again; the rest of the module keeps its bytes and stays where it is in
+    /// memory.
+    pub(crate) fn retarget_imports(&mut self, target: &str) -> Result<Vec<RewrittenImport>> {
+        let mut rewritten_imports = Vec::new();
+        let Some((section, original)) = self.imports.take() else {
+            return Ok(rewritten_imports);
+        };
+
+        let contents = &self.module[section.end - original.len()..section.end];
+        let imports = retarget_imports(
+            target,
+            ImportSectionReader::new(contents, original.start)?,
+            &mut rewritten_imports,
+        )?;
+        let mut encoded = vec![imports.id()];
+        imports.encode(&mut encoded);
+        self.module.splice(section, encoded);
+
+        Ok(rewritten_imports)
+    }
+}
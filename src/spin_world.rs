@@ -0,0 +1,121 @@
+use {
+    crate::{ComponentizeError, Result},
+    serde::Serialize,
+    std::fs,
+    wasm_compose::{
+        composer::{ComponentComposer, ROOT_COMPONENT_NAME},
+        config::{Config, Dependency, InstantiationArg},
+        graph::Component,
+    },
+    wasmparser::{Validator, WasmFeatures},
+};
+
+/// Exports `wasi:http/incoming-handler@0.2.0` by calling an imported `fermyon:spin/inbound-http`
+static WASI_HTTP_SHIM: &[u8] = include_bytes!("../adapters/wasi_http_shim.wasm");
+/// Exports `fermyon:spin/config` by calling an imported `fermyon:spin/variables@2.0.0`
+static VARIABLES_SHIM: &[u8] = include_bytes!("../adapters/variables_shim.wasm");
+
+static INBOUND_HTTP_INTERFACE: &str = "fermyon:spin/inbound-http";
+static CONFIG_INTERFACE: &str = "fermyon:spin/config";
+
+static MODULE: &str = "module";
+static VARIABLES: &str = "variables-shim";
+
+/// Like [`crate::EXPORT_INTERFACES`], but for [`SpinWorld::V2_0_0`], which has no Redis trigger
+pub(crate) static V2_EXPORT_INTERFACES: &[(&str, &str)] =
+    &[("handle-http-request", "inbound-http")];
+
+/// The Spin world a wit-bindgen 0.2 module is componentized against
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpinWorld {
+    /// The unversioned `reactor` world, exporting `fermyon:spin/inbound-http` and `fermyon:spin/inbound-redis`
+    #[default]
+    Reactor,
+    /// The `fermyon:spin@2.0.0` worlds: the component exports `handle-http-request` as
+    /// `wasi:http/incoming-handler@0.2.0` and imports `fermyon:spin/variables@2.0.0` in place of
+    /// `fermyon:spin/config`
+    ///
+    /// `handle-redis-message` and any exports registered with [`crate::ExportRegistry`] have no counterpart in
+    /// these worlds, so they are treated as unrecognized.  The other Spin interfaces are still imported unversioned.
+    V2_0_0,
+}
+
+/// Wrap `component`, as componentized against the `reactor` world, so that it targets the `fermyon:spin@2.0.0`
+/// worlds instead
+///
+/// Its `fermyon:spin/inbound-http` export and `fermyon:spin/config` import are connected to components which
+/// translate them to and from their 2.0 counterparts, and whose remaining imports become those of the result.
+pub(crate) fn compose_v2(component: &[u8]) -> Result<Vec<u8>> {
+    compose_v2_shims(component).map_err(ComponentizeError::Encode)
+}
+
+fn compose_v2_shims(component: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (config_import, inbound_http_export) = {
+        let component = Component::from_bytes(MODULE, component)?;
+        (
+            component.import_by_name(CONFIG_INTERFACE).is_some(),
+            component.export_by_name(INBOUND_HTTP_INTERFACE).is_some(),
+        )
+    };
+
+    // The composed component exports whatever its root does, so the wasi:http shim becomes the root if there is
+    // an HTTP handler to export.  Without one there is nothing to export, since the 2.0 worlds have no other
+    // trigger.
+    let mut components = Vec::new();
+    let mut arguments = Vec::new();
+    let module = if inbound_http_export {
+        components.extend([(ROOT_COMPONENT_NAME, WASI_HTTP_SHIM), (MODULE, component)]);
+        arguments.push((ROOT_COMPONENT_NAME, INBOUND_HTTP_INTERFACE, MODULE));
+        MODULE
+    } else {
+        components.push((ROOT_COMPONENT_NAME, component));
+        ROOT_COMPONENT_NAME
+    };
+    if config_import {
+        components.push((VARIABLES, VARIABLES_SHIM));
+        arguments.push((module, CONFIG_INTERFACE, VARIABLES));
+    }
+    if arguments.is_empty() {
+        return Ok(component.to_vec());
+    }
+
+    // `ComponentComposer` only reads components from disk, but unlike `CompositionGraph::encode` it merges the
+    // resources which the module and the shims import from the same WASI interfaces.
+    let dir = tempfile::tempdir()?;
+    let mut config = Config {
+        dir: dir.path().to_owned(),
+        ..Config::default()
+    };
+    for (name, bytes) in components {
+        let path = dir.path().join(name).with_extension("wasm");
+        fs::write(&path, bytes)?;
+        config.dependencies.insert(name.into(), Dependency { path });
+    }
+    for (name, import, instance) in arguments {
+        // Each argument is exported under the same name it is imported by
+        config
+            .instantiations
+            .entry(name.into())
+            .or_default()
+            .arguments
+            .insert(
+                import.into(),
+                InstantiationArg {
+                    instance: instance.into(),
+                    export: Some(import.into()),
+                },
+            );
+    }
+
+    let composed = ComponentComposer::new(&config.dependencies[ROOT_COMPONENT_NAME].path, &config)
+        .compose()?;
+
+    Validator::new_with_features(WasmFeatures {
+        component_model: true,
+        ..Default::default()
+    })
+    .validate_all(&composed)?;
+
+    Ok(composed)
+}
@@ -19,9 +19,17 @@ pub enum Command {
     Config {
         key: String,
     },
+    /// Get a variable via `fermyon:spin/variables@2.0.0`
+    Variables {
+        name: String,
+    },
     Http {
         url: String,
     },
+    /// Open a `fermyon:spin/redis@2.0.0` connection
+    RedisOpen {
+        address: String,
+    },
     RedisPublish {
         address: String,
         key: String,
@@ -63,6 +71,10 @@ pub enum Command {
         command: String,
         params: Vec<String>,
     },
+    /// Open a `fermyon:spin/postgres@2.0.0` connection
+    PostgresOpen {
+        address: String,
+    },
     PostgresExecute {
         address: String,
         statement: String,
@@ -73,6 +85,10 @@ pub enum Command {
         statement: String,
         params: Vec<String>,
     },
+    /// Open a `fermyon:spin/mysql@2.0.0` connection
+    MysqlOpen {
+        address: String,
+    },
     MysqlExecute {
         address: String,
         statement: String,
@@ -83,6 +99,25 @@ pub enum Command {
         statement: String,
         params: Vec<String>,
     },
+    SqliteOpen {
+        database: String,
+        /// The error `open` is expected to fail with, if any
+        error: Option<String>,
+    },
+    SqliteExecute {
+        connection: u32,
+        statement: String,
+        params: Vec<String>,
+    },
+    SqliteClose {
+        connection: u32,
+    },
+    /// Open the `fermyon:spin/sqlite@2.0.0` connection to `database` and execute `statement` on it
+    SqliteConnectionExecute {
+        database: String,
+        statement: String,
+        params: Vec<String>,
+    },
     KeyValueOpen {
         name: String,
     },
@@ -109,9 +144,36 @@ pub enum Command {
     KeyValueClose {
         store: u32,
     },
+    /// Open the `fermyon:spin/key-value@2.0.0` store labeled `label` and get `key` from it
+    KeyValueStoreGet {
+        label: String,
+        key: String,
+    },
+    KeyValueStoreSet {
+        label: String,
+        key: String,
+        value: String,
+    },
+    KeyValueStoreDelete {
+        label: String,
+        key: String,
+    },
+    KeyValueStoreExists {
+        label: String,
+        key: String,
+    },
+    KeyValueStoreGetKeys {
+        label: String,
+    },
     LlmInfer {
         model: String,
         prompt: String,
+        /// `name:value` pairs for every field of `inferencing-params`, or nothing to pass `none`
+        params: Vec<String>,
+    },
+    LlmGenerateEmbeddings {
+        model: String,
+        text: Vec<String>,
     },
     WasiEnv {
         key: String,
@@ -130,7 +192,14 @@ pub enum Command {
     },
 }
 
+/// The payload the inbound Redis test publishes to a guest's `handle-message` export
+///
+/// Every other message is a command sent by `InvocationStyle::InboundRedis`, so a guest which handles commands over
+/// Redis should acknowledge this one with `ok` rather than try to parse it.
+pub const INBOUND_REDIS_MESSAGE: &[u8] = b"Hello, SpinRedis!";
+
 impl Command {
+    /// Parse a command from an HTTP request body or Redis message payload, whose arguments are separated by "%20"
     pub fn extract(body: Option<Vec<u8>>) -> anyhow::Result<Command> {
         let body = body.ok_or_else(|| anyhow::anyhow!("empty request body"))?;
         let command = iter::once("<wasm module>")
@@ -139,6 +208,11 @@ impl Command {
         Ok(Cli::try_parse_from(command)?.command)
     }
 
+    /// Parse a command from the arguments of this process, as passed by `InvocationStyle::Command`
+    pub fn from_args() -> anyhow::Result<Command> {
+        Ok(Cli::try_parse_from(env::args())?.command)
+    }
+
     pub fn read_dir(dir_name: String) -> anyhow::Result<()> {
         let mut comma = false;
         Ok(for entry in fs::read_dir(dir_name)? {
@@ -203,6 +277,67 @@ impl Command {
     }
 }
 
+/// The fields of `llm::inferencing-params`
+#[derive(Debug, Copy, Clone)]
+pub struct InferencingParams {
+    pub max_tokens: u32,
+    pub repeat_penalty: f32,
+    pub repeat_penalty_last_n_token_count: u32,
+    pub temperature: f32,
+    pub top_k: u32,
+    pub top_p: f32,
+}
+
+impl InferencingParams {
+    /// Parse `name:value` pairs naming every field, returning `None` if there are no pairs at all
+    pub fn parse(params: &[String]) -> anyhow::Result<Option<Self>> {
+        if params.is_empty() {
+            return Ok(None);
+        }
+
+        let (mut max_tokens, mut repeat_penalty, mut repeat_penalty_last_n_token_count) =
+            (None, None, None);
+        let (mut temperature, mut top_k, mut top_p) = (None, None, None);
+        for param in params {
+            let (name, value) = split_param(param)?;
+            match name {
+                "max-tokens" => max_tokens = Some(value.parse()?),
+                "repeat-penalty" => repeat_penalty = Some(value.parse()?),
+                "repeat-penalty-last-n-token-count" => {
+                    repeat_penalty_last_n_token_count = Some(value.parse()?)
+                }
+                "temperature" => temperature = Some(value.parse()?),
+                "top-k" => top_k = Some(value.parse()?),
+                "top-p" => top_p = Some(value.parse()?),
+                _ => anyhow::bail!("unknown inferencing parameter: {name}"),
+            }
+        }
+
+        let missing = |name| anyhow::anyhow!("missing inferencing parameter: {name}");
+        Ok(Some(Self {
+            max_tokens: max_tokens.ok_or_else(|| missing("max-tokens"))?,
+            repeat_penalty: repeat_penalty.ok_or_else(|| missing("repeat-penalty"))?,
+            repeat_penalty_last_n_token_count: repeat_penalty_last_n_token_count
+                .ok_or_else(|| missing("repeat-penalty-last-n-token-count"))?,
+            temperature: temperature.ok_or_else(|| missing("temperature"))?,
+            top_k: top_k.ok_or_else(|| missing("top-k"))?,
+            top_p: top_p.ok_or_else(|| missing("top-p"))?,
+        }))
+    }
+}
+
+/// Check that a host function failed with the error named by `expected`, or succeeded if `expected` is `None`
+pub fn check_error(expected: Option<&str>, result: Result<(), String>) -> anyhow::Result<()> {
+    match (expected, result) {
+        (None, Ok(())) => Ok(()),
+        (Some(expected), Err(actual)) if expected == actual => Ok(()),
+        (None, Err(actual)) => Err(anyhow::anyhow!("unexpected error: {actual}")),
+        (Some(expected), result) => {
+            Err(anyhow::anyhow!("expected error {expected}, got {result:?}"))
+        }
+    }
+}
+
 pub fn split_param(param: &str) -> Result<(&str, &str), anyhow::Error> {
     let (type_, value) = param
         .split_once(':')
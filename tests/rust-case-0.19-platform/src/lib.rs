@@ -0,0 +1,418 @@
+use anyhow::{anyhow, bail, Result};
+use case_helper::Command;
+use exports::wasi::http0_2_0::incoming_handler;
+use fermyon::spin2_0_0::{key_value, mysql, postgres, rdbms_types, redis, sqlite, variables};
+use std::io::{self, Write};
+use wasi::{
+    http0_2_0::{
+        outgoing_handler,
+        types::{
+            self as http_types, Fields, IncomingRequest, OutgoingBody, OutgoingResponse,
+            ResponseOutparam,
+        },
+    },
+    io0_2_0::streams::{InputStream, StreamError},
+};
+
+wit_bindgen::generate!({
+    world: "fermyon:spin/http-trigger@2.0.0",
+    path: "../../wit",
+    exports: {
+        "wasi:http/incoming-handler@0.2.0": Spin,
+    },
+});
+
+struct Spin;
+
+impl incoming_handler::Guest for Spin {
+    fn handle(request: IncomingRequest, response_out: ResponseOutparam) {
+        let (status, headers, body) = match handle_request(request) {
+            Ok(response) => response,
+            Err(e) => (500, Vec::new(), format!("{e:?}").into_bytes()),
+        };
+
+        let response = OutgoingResponse::new(Fields::from_list(&headers).unwrap());
+        response.set_status_code(status).unwrap();
+        let outgoing_body = response.body().unwrap();
+        ResponseOutparam::set(response_out, Ok(response));
+
+        let stream = outgoing_body.write().unwrap();
+        for chunk in body.chunks(4096) {
+            stream.blocking_write_and_flush(chunk).unwrap();
+        }
+        drop(stream);
+        OutgoingBody::finish(outgoing_body, None).unwrap();
+    }
+}
+
+/// The status, headers and body to respond to `request` with
+fn handle_request(request: IncomingRequest) -> Result<(u16, Vec<(String, Vec<u8>)>, Vec<u8>)> {
+    let headers = request.headers().entries();
+    let path = request.path_with_query();
+    let post = matches!(request.method(), http_types::Method::Post);
+    let body = request
+        .consume()
+        .map_err(|()| anyhow!("request body already consumed"))?;
+    let stream = body
+        .stream()
+        .map_err(|()| anyhow!("request body stream already taken"))?;
+    let body = read_all(&stream)?;
+
+    Ok(if !post {
+        (405, Vec::new(), Vec::new())
+    } else if path.as_deref() == Some("/") {
+        dispatch(body)
+    } else if path.as_deref() != Some("/foo") {
+        (404, Vec::new(), Vec::new())
+    } else if headers != [("foo".into(), b"bar".to_vec())] || body != b"Hello, SpinHttp!" {
+        (400, Vec::new(), Vec::new())
+    } else {
+        (
+            200,
+            vec![("lorem".into(), b"ipsum".to_vec())],
+            b"dolor sit amet".to_vec(),
+        )
+    })
+}
+
+fn dispatch(body: Vec<u8>) -> (u16, Vec<(String, Vec<u8>)>, Vec<u8>) {
+    match execute(Some(body)) {
+        Ok(()) => {
+            _ = io::stdout().flush();
+            _ = io::stderr().flush();
+
+            (200, Vec::new(), Vec::new())
+        }
+
+        Err(e) => (500, Vec::new(), format!("{e:?}").into_bytes()),
+    }
+}
+
+fn read_all(stream: &InputStream) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match stream.blocking_read(4096) {
+            Ok(chunk) => bytes.extend(chunk),
+            Err(StreamError::Closed) => break Ok(bytes),
+            Err(StreamError::LastOperationFailed(e)) => bail!("{}", e.to_debug_string()),
+        }
+    }
+}
+
+fn execute(body: Option<Vec<u8>>) -> Result<()> {
+    let command = Command::extract(body)?;
+    match command {
+        Command::Variables { name } => {
+            variables::get(&name)?;
+        }
+
+        Command::Http { url } => send_request(&url)?,
+
+        Command::RedisOpen { address } => {
+            redis::Connection::open(&address)?;
+        }
+
+        Command::RedisPublish {
+            address,
+            key,
+            value,
+        } => {
+            redis::Connection::open(&address)?.publish(&key, &value.into_bytes())?;
+        }
+
+        Command::RedisSet {
+            address,
+            key,
+            value,
+        } => {
+            redis::Connection::open(&address)?.set(&key, &value.into_bytes())?;
+        }
+
+        Command::RedisGet { address, key } => {
+            redis::Connection::open(&address)?.get(&key)?;
+        }
+
+        Command::RedisIncr { address, key } => {
+            redis::Connection::open(&address)?.incr(&key)?;
+        }
+
+        Command::RedisDel { address, keys } => {
+            redis::Connection::open(&address)?.del(&keys)?;
+        }
+
+        Command::RedisSadd {
+            address,
+            key,
+            params,
+        } => {
+            redis::Connection::open(&address)?.sadd(&key, &params)?;
+        }
+
+        Command::RedisSmembers { address, key } => {
+            redis::Connection::open(&address)?.smembers(&key)?;
+        }
+
+        Command::RedisSrem {
+            address,
+            key,
+            params,
+        } => {
+            redis::Connection::open(&address)?.srem(&key, &params)?;
+        }
+
+        Command::RedisExecute {
+            address,
+            command,
+            params,
+        } => {
+            redis::Connection::open(&address)?.execute(
+                &command,
+                &params
+                    .into_iter()
+                    .map(|s| redis::RedisParameter::Binary(s.into_bytes()))
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::PostgresOpen { address } => {
+            postgres::Connection::open(&address)?;
+        }
+
+        Command::PostgresExecute {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::Connection::open(&address)?.execute(
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_rdbms(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::PostgresQuery {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::Connection::open(&address)?.query(
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_rdbms(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlOpen { address } => {
+            mysql::Connection::open(&address)?;
+        }
+
+        Command::MysqlExecute {
+            address,
+            statement,
+            params,
+        } => {
+            mysql::Connection::open(&address)?.execute(
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_rdbms(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlQuery {
+            address,
+            statement,
+            params,
+        } => {
+            mysql::Connection::open(&address)?.query(
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_rdbms(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteOpen { database, error } => {
+            case_helper::check_error(
+                error.as_deref(),
+                sqlite::Connection::open(&database)
+                    .map(drop)
+                    .map_err(sqlite_error),
+            )?;
+        }
+
+        Command::SqliteConnectionExecute {
+            database,
+            statement,
+            params,
+        } => {
+            sqlite::Connection::open(&database)?.execute(
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_sqlite(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::KeyValueOpen { name } => {
+            key_value::Store::open(&name)?;
+        }
+
+        Command::KeyValueStoreGet { label, key } => {
+            key_value::Store::open(&label)?.get(&key)?;
+        }
+
+        Command::KeyValueStoreSet { label, key, value } => {
+            key_value::Store::open(&label)?.set(&key, value.as_bytes())?;
+        }
+
+        Command::KeyValueStoreDelete { label, key } => {
+            key_value::Store::open(&label)?.delete(&key)?;
+        }
+
+        Command::KeyValueStoreExists { label, key } => {
+            key_value::Store::open(&label)?.exists(&key)?;
+        }
+
+        Command::KeyValueStoreGetKeys { label } => {
+            key_value::Store::open(&label)?.get_keys()?;
+        }
+
+        Command::Config { .. }
+        | Command::SqliteExecute { .. }
+        | Command::SqliteClose { .. }
+        | Command::KeyValueGet { .. }
+        | Command::KeyValueSet { .. }
+        | Command::KeyValueDelete { .. }
+        | Command::KeyValueExists { .. }
+        | Command::KeyValueGetKeys { .. }
+        | Command::KeyValueClose { .. }
+        | Command::LlmInfer { .. }
+        | Command::LlmGenerateEmbeddings { .. } => {
+            bail!("this module only implements the commands tested against fermyon:spin@2.0.0")
+        }
+
+        Command::WasiEnv { key } => Command::env(key)?,
+        Command::WasiEpoch => Command::epoch()?,
+        Command::WasiRandom => Command::random()?,
+        Command::WasiStdio => Command::stdio()?,
+        Command::WasiRead { file_name } => Command::read(file_name)?,
+        Command::WasiReaddir { dir_name } => Command::read_dir(dir_name)?,
+        Command::WasiStat { file_name } => Command::stat(file_name)?,
+    }
+
+    Ok(())
+}
+
+/// Send a GET request for `url` via `wasi:http/outgoing-handler` and read the whole response body
+fn send_request(url: &str) -> Result<()> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| anyhow!("invalid URL: {url}"))?;
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let scheme = match scheme {
+        "http" => http_types::Scheme::Http,
+        "https" => http_types::Scheme::Https,
+        other => http_types::Scheme::Other(other.into()),
+    };
+
+    let request = http_types::OutgoingRequest::new(Fields::new());
+    request
+        .set_method(&http_types::Method::Get)
+        .map_err(|()| anyhow!("invalid method"))?;
+    request
+        .set_scheme(Some(&scheme))
+        .map_err(|()| anyhow!("invalid scheme"))?;
+    request
+        .set_authority(Some(authority))
+        .map_err(|()| anyhow!("invalid authority: {authority}"))?;
+    request
+        .set_path_with_query(Some(if path.is_empty() { "/" } else { path }))
+        .map_err(|()| anyhow!("invalid path: {path}"))?;
+
+    let response = outgoing_handler::handle(request, None).map_err(|e| anyhow!("{e:?}"))?;
+    response.subscribe().block();
+    let response = response
+        .get()
+        .ok_or_else(|| anyhow!("response not ready"))?
+        .map_err(|()| anyhow!("response already taken"))?
+        .map_err(|e| anyhow!("{e:?}"))?;
+
+    if response.status() != 200 {
+        bail!("unexpected status: {}", response.status());
+    }
+
+    let body = response
+        .consume()
+        .map_err(|()| anyhow!("response body already consumed"))?;
+    let stream = body
+        .stream()
+        .map_err(|()| anyhow!("response body stream already taken"))?;
+    read_all(&stream)?;
+
+    Ok(())
+}
+
+fn parse_rdbms(param: &str) -> Result<rdbms_types::ParameterValue> {
+    use rdbms_types::ParameterValue as PV;
+
+    Ok(if param == "null" {
+        PV::DbNull
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "boolean" => PV::Boolean(value.parse()?),
+            "int8" => PV::Int8(value.parse()?),
+            "int16" => PV::Int16(value.parse()?),
+            "int32" => PV::Int32(value.parse()?),
+            "int64" => PV::Int64(value.parse()?),
+            "uint8" => PV::Uint8(value.parse()?),
+            "uint16" => PV::Uint16(value.parse()?),
+            "uint32" => PV::Uint32(value.parse()?),
+            "uint64" => PV::Uint64(value.parse()?),
+            "floating32" => PV::Floating32(value.parse()?),
+            "floating64" => PV::Floating64(value.parse()?),
+            "str" => PV::Str(value.to_owned()),
+            "binary" => PV::Binary(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn parse_sqlite(param: &str) -> Result<sqlite::Value> {
+    use sqlite::Value as V;
+
+    Ok(if param == "null" {
+        V::Null
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "integer" => V::Integer(value.parse()?),
+            "real" => V::Real(value.parse()?),
+            "text" => V::Text(value.to_owned()),
+            "blob" => V::Blob(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn sqlite_error(error: sqlite::Error) -> String {
+    use sqlite::Error;
+
+    match error {
+        Error::NoSuchDatabase => "no-such-database".into(),
+        Error::AccessDenied => "access-denied".into(),
+        Error::InvalidConnection => "invalid-connection".into(),
+        Error::DatabaseFull => "database-full".into(),
+        Error::Io(message) => format!("io({message})"),
+    }
+}
@@ -0,0 +1,439 @@
+use anyhow::{anyhow, bail, Result};
+use case_helper::Command;
+use exports::wasi::http0_2_0::incoming_handler;
+use fermyon::spin::{
+    self,
+    http_types::{Method, Request},
+    mysql, postgres,
+};
+use std::io::{self, Write};
+use wasi::{
+    http0_2_0::types::{
+        self as http_types, Fields, IncomingRequest, OutgoingBody, OutgoingResponse,
+        ResponseOutparam,
+    },
+    io0_2_0::streams::{InputStream, StreamError},
+};
+
+// The Spin 1.x imports of `fermyon:spin/host`, but triggered through `wasi:http` rather than `inbound-http`
+wit_bindgen::generate!({
+    inline: "
+        package spin-test:wasi-http;
+
+        world host-wasi-http {
+            include fermyon:spin/platform;
+            export wasi:http/incoming-handler@0.2.0;
+        }
+    ",
+    world: "spin-test:wasi-http/host-wasi-http",
+    path: "../../wit",
+    exports: {
+        "wasi:http/incoming-handler@0.2.0": Spin,
+    },
+});
+
+struct Spin;
+
+impl incoming_handler::Guest for Spin {
+    fn handle(request: IncomingRequest, response_out: ResponseOutparam) {
+        let (status, headers, body) = match handle_request(request) {
+            Ok(response) => response,
+            Err(e) => (500, Vec::new(), format!("{e:?}").into_bytes()),
+        };
+
+        let response = OutgoingResponse::new(Fields::from_list(&headers).unwrap());
+        response.set_status_code(status).unwrap();
+        let outgoing_body = response.body().unwrap();
+        ResponseOutparam::set(response_out, Ok(response));
+
+        let stream = outgoing_body.write().unwrap();
+        for chunk in body.chunks(4096) {
+            stream.blocking_write_and_flush(chunk).unwrap();
+        }
+        drop(stream);
+        OutgoingBody::finish(outgoing_body, None).unwrap();
+    }
+}
+
+/// The status, headers and body to respond to `request` with
+fn handle_request(request: IncomingRequest) -> Result<(u16, Vec<(String, Vec<u8>)>, Vec<u8>)> {
+    let headers = request.headers().entries();
+    let path = request.path_with_query();
+    let post = matches!(request.method(), http_types::Method::Post);
+    let body = request
+        .consume()
+        .map_err(|()| anyhow!("request body already consumed"))?;
+    let stream = body
+        .stream()
+        .map_err(|()| anyhow!("request body stream already taken"))?;
+    let body = read_all(&stream)?;
+
+    Ok(if !post {
+        (405, Vec::new(), Vec::new())
+    } else if path.as_deref() == Some("/") {
+        dispatch(body)
+    } else if path.as_deref() != Some("/foo") {
+        (404, Vec::new(), Vec::new())
+    } else if headers != [("foo".into(), b"bar".to_vec())] || body != b"Hello, SpinHttp!" {
+        (400, Vec::new(), Vec::new())
+    } else {
+        (
+            200,
+            vec![("lorem".into(), b"ipsum".to_vec())],
+            b"dolor sit amet".to_vec(),
+        )
+    })
+}
+
+fn dispatch(body: Vec<u8>) -> (u16, Vec<(String, Vec<u8>)>, Vec<u8>) {
+    match execute(Some(body)) {
+        Ok(()) => {
+            _ = io::stdout().flush();
+            _ = io::stderr().flush();
+
+            (200, Vec::new(), Vec::new())
+        }
+
+        Err(e) => (500, Vec::new(), format!("{e:?}").into_bytes()),
+    }
+}
+
+fn read_all(stream: &InputStream) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match stream.blocking_read(4096) {
+            Ok(chunk) => bytes.extend(chunk),
+            Err(StreamError::Closed) => break Ok(bytes),
+            Err(StreamError::LastOperationFailed(e)) => bail!("{}", e.to_debug_string()),
+        }
+    }
+}
+
+fn execute(body: Option<Vec<u8>>) -> Result<()> {
+    let command = Command::extract(body)?;
+    match command {
+        Command::Config { key } => {
+            spin::config::get_config(&key)?;
+        }
+
+        Command::Http { url } => {
+            spin::http::send_request(&Request {
+                method: Method::Get,
+                uri: url,
+                headers: Vec::new(),
+                params: Vec::new(),
+                body: None,
+            })?;
+        }
+
+        Command::RedisPublish {
+            address,
+            key,
+            value,
+        } => {
+            spin::redis::publish(&address, &key, &value.into_bytes())?;
+        }
+
+        Command::RedisSet {
+            address,
+            key,
+            value,
+        } => {
+            spin::redis::set(&address, &key, &value.into_bytes())?;
+        }
+
+        Command::RedisGet { address, key } => {
+            spin::redis::get(&address, &key)?;
+        }
+
+        Command::RedisIncr { address, key } => {
+            spin::redis::incr(&address, &key)?;
+        }
+
+        Command::RedisDel { address, keys } => {
+            spin::redis::del(&address, &keys)?;
+        }
+
+        Command::RedisSadd {
+            address,
+            key,
+            params,
+        } => {
+            spin::redis::sadd(&address, &key, &params)?;
+        }
+
+        Command::RedisSmembers { address, key } => {
+            spin::redis::smembers(&address, &key)?;
+        }
+
+        Command::RedisSrem {
+            address,
+            key,
+            params,
+        } => {
+            spin::redis::srem(&address, &key, &params)?;
+        }
+
+        Command::RedisExecute {
+            address,
+            command,
+            params,
+        } => {
+            spin::redis::execute(
+                &address,
+                &command,
+                &params
+                    .into_iter()
+                    .map(|s| spin::redis_types::RedisParameter::Binary(s.into_bytes()))
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::PostgresExecute {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::execute(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_pg(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::PostgresQuery {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::query(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_pg(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlExecute {
+            address,
+            statement,
+            params,
+        } => {
+            mysql::execute(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_mysql(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlQuery {
+            address,
+            statement,
+            params,
+        } => {
+            spin::mysql::query(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_mysql(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteOpen { database, error } => {
+            case_helper::check_error(
+                error.as_deref(),
+                spin::sqlite::open(&database)
+                    .map(drop)
+                    .map_err(sqlite_error),
+            )?;
+        }
+
+        Command::SqliteExecute {
+            connection,
+            statement,
+            params,
+        } => {
+            spin::sqlite::execute(
+                connection,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_sqlite(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteClose { connection } => {
+            spin::sqlite::close(connection);
+        }
+
+        Command::KeyValueOpen { name } => {
+            spin::key_value::open(&name)?;
+        }
+
+        Command::KeyValueGet { store, key } => {
+            spin::key_value::get(store, &key)?;
+        }
+
+        Command::KeyValueSet { store, key, value } => {
+            spin::key_value::set(store, &key, value.as_bytes())?;
+        }
+
+        Command::KeyValueDelete { store, key } => {
+            spin::key_value::delete(store, &key)?;
+        }
+
+        Command::KeyValueExists { store, key } => {
+            spin::key_value::exists(store, &key)?;
+        }
+
+        Command::KeyValueGetKeys { store } => {
+            spin::key_value::get_keys(store)?;
+        }
+
+        Command::KeyValueClose { store } => {
+            spin::key_value::close(store);
+        }
+        Command::LlmInfer {
+            model,
+            prompt,
+            params,
+        } => {
+            let _ = spin::llm::infer(
+                &model,
+                &prompt,
+                case_helper::InferencingParams::parse(&params)?.map(|params| {
+                    spin::llm::InferencingParams {
+                        max_tokens: params.max_tokens,
+                        repeat_penalty: params.repeat_penalty,
+                        repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count,
+                        temperature: params.temperature,
+                        top_k: params.top_k,
+                        top_p: params.top_p,
+                    }
+                }),
+            );
+        }
+
+        Command::LlmGenerateEmbeddings { model, text } => {
+            let _ = spin::llm::generate_embeddings(&model, &text);
+        }
+
+        Command::Variables { .. }
+        | Command::RedisOpen { .. }
+        | Command::PostgresOpen { .. }
+        | Command::MysqlOpen { .. }
+        | Command::KeyValueStoreGet { .. }
+        | Command::KeyValueStoreSet { .. }
+        | Command::KeyValueStoreDelete { .. }
+        | Command::KeyValueStoreExists { .. }
+        | Command::KeyValueStoreGetKeys { .. }
+        | Command::SqliteConnectionExecute { .. } => {
+            bail!("this module does not import the fermyon:spin@2.0.0 interfaces")
+        }
+
+        Command::WasiEnv { key } => Command::env(key)?,
+        Command::WasiEpoch => Command::epoch()?,
+        Command::WasiRandom => Command::random()?,
+        Command::WasiStdio => Command::stdio()?,
+        Command::WasiRead { file_name } => Command::read(file_name)?,
+        Command::WasiReaddir { dir_name } => Command::read_dir(dir_name)?,
+        Command::WasiStat { file_name } => Command::stat(file_name)?,
+    }
+
+    Ok(())
+}
+
+fn parse_pg(param: &str) -> Result<spin::postgres::ParameterValue> {
+    use spin::postgres::ParameterValue as PV;
+
+    Ok(if param == "null" {
+        PV::DbNull
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "boolean" => PV::Boolean(value.parse()?),
+            "int8" => PV::Int8(value.parse()?),
+            "int16" => PV::Int16(value.parse()?),
+            "int32" => PV::Int32(value.parse()?),
+            "int64" => PV::Int64(value.parse()?),
+            "uint8" => PV::Uint8(value.parse()?),
+            "uint16" => PV::Uint16(value.parse()?),
+            "uint32" => PV::Uint32(value.parse()?),
+            "uint64" => PV::Uint64(value.parse()?),
+            "floating32" => PV::Floating32(value.parse()?),
+            "floating64" => PV::Floating64(value.parse()?),
+            "str" => PV::Str(value.to_owned()),
+            "binary" => PV::Binary(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn parse_mysql(param: &str) -> Result<spin::mysql::ParameterValue> {
+    use spin::mysql::ParameterValue as PV;
+
+    Ok(if param == "null" {
+        PV::DbNull
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "boolean" => PV::Boolean(value.parse()?),
+            "int8" => PV::Int8(value.parse()?),
+            "int16" => PV::Int16(value.parse()?),
+            "int32" => PV::Int32(value.parse()?),
+            "int64" => PV::Int64(value.parse()?),
+            "uint8" => PV::Uint8(value.parse()?),
+            "uint16" => PV::Uint16(value.parse()?),
+            "uint32" => PV::Uint32(value.parse()?),
+            "uint64" => PV::Uint64(value.parse()?),
+            "floating32" => PV::Floating32(value.parse()?),
+            "floating64" => PV::Floating64(value.parse()?),
+            "str" => PV::Str(value.to_owned()),
+            "binary" => PV::Binary(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn parse_sqlite(param: &str) -> Result<spin::sqlite::Value> {
+    use spin::sqlite::Value as V;
+
+    Ok(if param == "null" {
+        V::Null
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "integer" => V::Integer(value.parse()?),
+            "real" => V::Real(value.parse()?),
+            "text" => V::Text(value.to_owned()),
+            "blob" => V::Blob(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn sqlite_error(error: spin::sqlite::Error) -> String {
+    use spin::sqlite::Error;
+
+    match error {
+        Error::NoSuchDatabase => "no-such-database".into(),
+        Error::AccessDenied => "access-denied".into(),
+        Error::InvalidConnection => "invalid-connection".into(),
+        Error::DatabaseFull => "database-full".into(),
+        Error::Io(message) => format!("io({message})"),
+    }
+}
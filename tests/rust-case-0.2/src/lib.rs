@@ -152,6 +152,22 @@ impl fmt::Display for key_value::Error {
 
 impl error::Error for key_value::Error {}
 
+wit_bindgen_rust::import!("../wit/sqlite.wit");
+
+impl fmt::Display for sqlite::Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoSuchDatabase => f.write_str("no such database"),
+            Self::AccessDenied => f.write_str("access denied"),
+            Self::InvalidConnection => f.write_str("invalid connection"),
+            Self::DatabaseFull => f.write_str("database full"),
+            Self::Io(message) => write!(f, "io error: {message}"),
+        }
+    }
+}
+
+impl error::Error for sqlite::Error {}
+
 wit_bindgen_rust::import!("../wit/llm.wit");
 
 fn dispatch(body: Option<Vec<u8>>) -> Response {
@@ -327,6 +343,32 @@ fn execute(body: Option<Vec<u8>>) -> Result<()> {
             )?;
         }
 
+        Command::SqliteOpen { database, error } => {
+            case_helper::check_error(
+                error.as_deref(),
+                sqlite::open(database).map(drop).map_err(sqlite_error),
+            )?;
+        }
+
+        Command::SqliteExecute {
+            connection,
+            statement,
+            params,
+        } => {
+            sqlite::execute(
+                *connection,
+                statement,
+                &params
+                    .iter()
+                    .map(|param| parse_sqlite(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteClose { connection } => {
+            sqlite::close(*connection);
+        }
+
         Command::KeyValueOpen { name } => {
             key_value::open(name)?;
         }
@@ -354,8 +396,45 @@ fn execute(body: Option<Vec<u8>>) -> Result<()> {
         Command::KeyValueClose { store } => {
             key_value::close(*store);
         }
-        Command::LlmInfer { model, prompt } => {
-            let _ = llm::infer(model, prompt, None);
+        Command::LlmInfer {
+            model,
+            prompt,
+            params,
+        } => {
+            let _ = llm::infer(
+                model,
+                prompt,
+                case_helper::InferencingParams::parse(params)?.map(|params| {
+                    llm::InferencingParams {
+                        max_tokens: params.max_tokens,
+                        repeat_penalty: params.repeat_penalty,
+                        repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count,
+                        temperature: params.temperature,
+                        top_k: params.top_k,
+                        top_p: params.top_p,
+                    }
+                }),
+            );
+        }
+
+        Command::LlmGenerateEmbeddings { model, text } => {
+            let _ = llm::generate_embeddings(
+                model,
+                &text.iter().map(String::as_str).collect::<Vec<_>>(),
+            );
+        }
+
+        Command::Variables { .. }
+        | Command::RedisOpen { .. }
+        | Command::PostgresOpen { .. }
+        | Command::MysqlOpen { .. }
+        | Command::KeyValueStoreGet { .. }
+        | Command::KeyValueStoreSet { .. }
+        | Command::KeyValueStoreDelete { .. }
+        | Command::KeyValueStoreExists { .. }
+        | Command::KeyValueStoreGetKeys { .. }
+        | Command::SqliteConnectionExecute { .. } => {
+            bail!("this module does not import the fermyon:spin@2.0.0 interfaces")
         }
 
         Command::WasiEnv { key } => Command::env(key.clone())?,
@@ -423,3 +502,33 @@ fn parse_mysql(param: &str) -> Result<outbound_mysql::ParameterValue> {
         }
     })
 }
+
+fn parse_sqlite(param: &str) -> Result<sqlite::ValueParam> {
+    use sqlite::ValueParam as V;
+
+    Ok(if param == "null" {
+        V::Null
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "integer" => V::Integer(value.parse()?),
+            "real" => V::Real(value.parse()?),
+            "text" => V::Text(value),
+            "blob" => V::Blob(value.as_bytes()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn sqlite_error(error: sqlite::Error) -> String {
+    use sqlite::Error;
+
+    match error {
+        Error::NoSuchDatabase => "no-such-database".into(),
+        Error::AccessDenied => "access-denied".into(),
+        Error::InvalidConnection => "invalid-connection".into(),
+        Error::DatabaseFull => "database-full".into(),
+        Error::Io(message) => format!("io({message})"),
+    }
+}
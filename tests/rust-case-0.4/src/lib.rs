@@ -0,0 +1,458 @@
+use anyhow::{bail, Result};
+use case_helper::Command;
+use spin::{
+    http_types::{Method, Response},
+    inbound_http::Request,
+};
+use std::{
+    error, fmt,
+    io::{self, Write},
+    str,
+};
+
+#[macro_use]
+mod wit {
+    wit_bindgen::generate!({
+        world: "reactor",
+        path: "../wit-0.4",
+        macro_call_prefix: "wit::",
+    });
+}
+use wit as exports;
+use wit::{self as spin, mysql, postgres};
+
+struct Spin;
+export_reactor!(Spin);
+
+impl exports::inbound_http::InboundHttp for Spin {
+    fn handle_request(request: Request) -> Response {
+        if request.method != Method::Post {
+            Response {
+                status: 405,
+                headers: None,
+                body: None,
+            }
+        } else if request.uri == "/" {
+            dispatch(request.body)
+        } else if request.uri != "/foo" {
+            Response {
+                status: 404,
+                headers: None,
+                body: None,
+            }
+        } else if request.headers != [("foo".into(), "bar".into())]
+            || request.body.as_deref() != Some(b"Hello, SpinHttp!")
+        {
+            Response {
+                status: 400,
+                headers: None,
+                body: None,
+            }
+        } else {
+            Response {
+                status: 200,
+                headers: Some(vec![("lorem".into(), "ipsum".into())]),
+                body: Some("dolor sit amet".as_bytes().to_owned()),
+            }
+        }
+    }
+}
+
+impl exports::inbound_redis::InboundRedis for Spin {
+    fn handle_message(_body: Vec<u8>) -> Result<(), spin::redis::Error> {
+        Ok(())
+    }
+}
+
+// wit-bindgen 0.4 only implements `Error` for types used as errors by functions of the interface defining them
+
+impl fmt::Display for spin::http_types::HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "success",
+            Self::DestinationNotAllowed => "destination not allowed",
+            Self::InvalidUrl => "invalid url",
+            Self::RequestError => "request error",
+            Self::RuntimeError => "runtime error",
+            Self::TooManyRequests => "too many requests",
+        })
+    }
+}
+
+impl error::Error for spin::http_types::HttpError {}
+
+impl fmt::Display for spin::redis_types::Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Success => "success",
+            Self::Error => "redis error",
+        })
+    }
+}
+
+impl error::Error for spin::redis_types::Error {}
+
+pub fn dispatch(body: Option<Vec<u8>>) -> Response {
+    match execute(body) {
+        Ok(()) => {
+            _ = io::stdout().flush();
+            _ = io::stderr().flush();
+
+            Response {
+                status: 200,
+                headers: None,
+                body: None,
+            }
+        }
+
+        Err(e) => Response {
+            status: 500,
+            headers: None,
+            body: Some(format!("{e:?}").into_bytes()),
+        },
+    }
+}
+
+fn execute(body: Option<Vec<u8>>) -> Result<()> {
+    let command = Command::extract(body)?;
+    match command {
+        Command::Config { key } => {
+            spin::config::get_config(&key)?;
+        }
+
+        Command::Http { url } => {
+            spin::http::send_request(spin::http::Request {
+                method: Method::Get,
+                uri: &url,
+                headers: &[],
+                params: &[],
+                body: None,
+            })?;
+        }
+
+        Command::RedisPublish {
+            address,
+            key,
+            value,
+        } => {
+            spin::redis::publish(&address, &key, &value.into_bytes())?;
+        }
+
+        Command::RedisSet {
+            address,
+            key,
+            value,
+        } => {
+            spin::redis::set(&address, &key, &value.into_bytes())?;
+        }
+
+        Command::RedisGet { address, key } => {
+            spin::redis::get(&address, &key)?;
+        }
+
+        Command::RedisIncr { address, key } => {
+            spin::redis::incr(&address, &key)?;
+        }
+
+        Command::RedisDel { address, keys } => {
+            spin::redis::del(
+                &address,
+                &keys.iter().map(String::as_str).collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::RedisSadd {
+            address,
+            key,
+            params,
+        } => {
+            spin::redis::sadd(
+                &address,
+                &key,
+                &params.iter().map(String::as_str).collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::RedisSmembers { address, key } => {
+            spin::redis::smembers(&address, &key)?;
+        }
+
+        Command::RedisSrem {
+            address,
+            key,
+            params,
+        } => {
+            spin::redis::srem(
+                &address,
+                &key,
+                &params.iter().map(String::as_str).collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::RedisExecute {
+            address,
+            command,
+            params,
+        } => {
+            let params: Vec<_> = params.into_iter().map(|s| s.into_bytes()).collect();
+            spin::redis::execute(
+                &address,
+                &command,
+                &params
+                    .iter()
+                    .map(|s| spin::redis_types::RedisParameter::Binary(s))
+                    .collect::<Vec<_>>(),
+            )?;
+        }
+
+        Command::PostgresExecute {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::execute(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_pg(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::PostgresQuery {
+            address,
+            statement,
+            params,
+        } => {
+            postgres::query(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_pg(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlExecute {
+            address,
+            statement,
+            params,
+        } => {
+            mysql::execute(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_mysql(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::MysqlQuery {
+            address,
+            statement,
+            params,
+        } => {
+            spin::mysql::query(
+                &address,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_mysql(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteOpen { database, error } => {
+            case_helper::check_error(
+                error.as_deref(),
+                spin::sqlite::open(&database)
+                    .map(drop)
+                    .map_err(sqlite_error),
+            )?;
+        }
+
+        Command::SqliteExecute {
+            connection,
+            statement,
+            params,
+        } => {
+            spin::sqlite::execute(
+                connection,
+                &statement,
+                &params
+                    .iter()
+                    .map(|param| parse_sqlite(param))
+                    .collect::<Result<Vec<_>>>()?,
+            )?;
+        }
+
+        Command::SqliteClose { connection } => {
+            spin::sqlite::close(connection);
+        }
+
+        Command::KeyValueOpen { name } => {
+            spin::key_value::open(&name)?;
+        }
+
+        Command::KeyValueGet { store, key } => {
+            spin::key_value::get(store, &key)?;
+        }
+
+        Command::KeyValueSet { store, key, value } => {
+            spin::key_value::set(store, &key, value.as_bytes())?;
+        }
+
+        Command::KeyValueDelete { store, key } => {
+            spin::key_value::delete(store, &key)?;
+        }
+
+        Command::KeyValueExists { store, key } => {
+            spin::key_value::exists(store, &key)?;
+        }
+
+        Command::KeyValueGetKeys { store } => {
+            spin::key_value::get_keys(store)?;
+        }
+
+        Command::KeyValueClose { store } => {
+            spin::key_value::close(store);
+        }
+        Command::LlmInfer {
+            model,
+            prompt,
+            params,
+        } => {
+            let _ = spin::llm::infer(
+                &model,
+                &prompt,
+                case_helper::InferencingParams::parse(&params)?.map(|params| {
+                    spin::llm::InferencingParams {
+                        max_tokens: params.max_tokens,
+                        repeat_penalty: params.repeat_penalty,
+                        repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count,
+                        temperature: params.temperature,
+                        top_k: params.top_k,
+                        top_p: params.top_p,
+                    }
+                }),
+            );
+        }
+
+        Command::LlmGenerateEmbeddings { model, text } => {
+            let _ = spin::llm::generate_embeddings(
+                &model,
+                &text.iter().map(String::as_str).collect::<Vec<_>>(),
+            );
+        }
+
+        Command::Variables { .. }
+        | Command::RedisOpen { .. }
+        | Command::PostgresOpen { .. }
+        | Command::MysqlOpen { .. }
+        | Command::KeyValueStoreGet { .. }
+        | Command::KeyValueStoreSet { .. }
+        | Command::KeyValueStoreDelete { .. }
+        | Command::KeyValueStoreExists { .. }
+        | Command::KeyValueStoreGetKeys { .. }
+        | Command::SqliteConnectionExecute { .. } => {
+            bail!("this module does not import the fermyon:spin@2.0.0 interfaces")
+        }
+
+        Command::WasiEnv { key } => Command::env(key)?,
+        Command::WasiEpoch => Command::epoch()?,
+        Command::WasiRandom => Command::random()?,
+        Command::WasiStdio => Command::stdio()?,
+        Command::WasiRead { file_name } => Command::read(file_name)?,
+        Command::WasiReaddir { dir_name } => Command::read_dir(dir_name)?,
+        Command::WasiStat { file_name } => Command::stat(file_name)?,
+    }
+
+    Ok(())
+}
+
+fn parse_pg(param: &str) -> Result<spin::postgres::ParameterValue> {
+    use spin::postgres::ParameterValue as PV;
+
+    Ok(if param == "null" {
+        PV::DbNull
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "boolean" => PV::Boolean(value.parse()?),
+            "int8" => PV::Int8(value.parse()?),
+            "int16" => PV::Int16(value.parse()?),
+            "int32" => PV::Int32(value.parse()?),
+            "int64" => PV::Int64(value.parse()?),
+            "uint8" => PV::Uint8(value.parse()?),
+            "uint16" => PV::Uint16(value.parse()?),
+            "uint32" => PV::Uint32(value.parse()?),
+            "uint64" => PV::Uint64(value.parse()?),
+            "floating32" => PV::Floating32(value.parse()?),
+            "floating64" => PV::Floating64(value.parse()?),
+            "str" => PV::Str(value),
+            "binary" => PV::Binary(value.as_bytes()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn parse_mysql(param: &str) -> Result<spin::mysql::ParameterValue> {
+    use spin::mysql::ParameterValue as PV;
+
+    Ok(if param == "null" {
+        PV::DbNull
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "boolean" => PV::Boolean(value.parse()?),
+            "int8" => PV::Int8(value.parse()?),
+            "int16" => PV::Int16(value.parse()?),
+            "int32" => PV::Int32(value.parse()?),
+            "int64" => PV::Int64(value.parse()?),
+            "uint8" => PV::Uint8(value.parse()?),
+            "uint16" => PV::Uint16(value.parse()?),
+            "uint32" => PV::Uint32(value.parse()?),
+            "uint64" => PV::Uint64(value.parse()?),
+            "floating32" => PV::Floating32(value.parse()?),
+            "floating64" => PV::Floating64(value.parse()?),
+            "str" => PV::Str(value),
+            "binary" => PV::Binary(value.as_bytes()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn parse_sqlite(param: &str) -> Result<spin::sqlite::ValueParam> {
+    use spin::sqlite::ValueParam as V;
+
+    Ok(if param == "null" {
+        V::Null
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "integer" => V::Integer(value.parse()?),
+            "real" => V::Real(value.parse()?),
+            "text" => V::Text(value),
+            "blob" => V::Blob(value.as_bytes()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn sqlite_error(error: spin::sqlite::Error) -> String {
+    use spin::sqlite::Error;
+
+    match error {
+        Error::NoSuchDatabase => "no-such-database".into(),
+        Error::AccessDenied => "access-denied".into(),
+        Error::InvalidConnection => "invalid-connection".into(),
+        Error::DatabaseFull => "database-full".into(),
+        Error::Io(message) => format!("io({message})"),
+    }
+}
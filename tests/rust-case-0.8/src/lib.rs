@@ -233,6 +233,32 @@ fn execute(body: Option<Vec<u8>>) -> Result<()> {
             )?;
         }
 
+        Command::SqliteOpen { database, error } => {
+            case_helper::check_error(
+                error.as_deref(),
+                spin::sqlite::open(&database)
+                    .map(drop)
+                    .map_err(sqlite_error),
+            )?;
+        }
+
+        Command::SqliteExecute {
+            connection,
+            statement,
+            params,
+        } => {
+            let params = params
+                .iter()
+                .map(|param| parse_sqlite(param))
+                .collect::<Result<Vec<_>>>()?;
+
+            spin::sqlite::execute(connection, &statement, &params.iter().collect::<Vec<_>>())?;
+        }
+
+        Command::SqliteClose { connection } => {
+            spin::sqlite::close(connection);
+        }
+
         Command::KeyValueOpen { name } => {
             spin::key_value::open(&name)?;
         }
@@ -260,8 +286,45 @@ fn execute(body: Option<Vec<u8>>) -> Result<()> {
         Command::KeyValueClose { store } => {
             spin::key_value::close(store);
         }
-        Command::LlmInfer { model, prompt } => {
-            let _ = spin::llm::infer(&model, &prompt, None);
+        Command::LlmInfer {
+            model,
+            prompt,
+            params,
+        } => {
+            let _ = spin::llm::infer(
+                &model,
+                &prompt,
+                case_helper::InferencingParams::parse(&params)?.map(|params| {
+                    spin::llm::InferencingParams {
+                        max_tokens: params.max_tokens,
+                        repeat_penalty: params.repeat_penalty,
+                        repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count,
+                        temperature: params.temperature,
+                        top_k: params.top_k,
+                        top_p: params.top_p,
+                    }
+                }),
+            );
+        }
+
+        Command::LlmGenerateEmbeddings { model, text } => {
+            let _ = spin::llm::generate_embeddings(
+                &model,
+                &text.iter().map(String::as_str).collect::<Vec<_>>(),
+            );
+        }
+
+        Command::Variables { .. }
+        | Command::RedisOpen { .. }
+        | Command::PostgresOpen { .. }
+        | Command::MysqlOpen { .. }
+        | Command::KeyValueStoreGet { .. }
+        | Command::KeyValueStoreSet { .. }
+        | Command::KeyValueStoreDelete { .. }
+        | Command::KeyValueStoreExists { .. }
+        | Command::KeyValueStoreGetKeys { .. }
+        | Command::SqliteConnectionExecute { .. } => {
+            bail!("this module does not import the fermyon:spin@2.0.0 interfaces")
         }
 
         Command::WasiEnv { key } => Command::env(key)?,
@@ -329,3 +392,33 @@ fn parse_mysql(param: &str) -> Result<spin::mysql::ParameterValue> {
         }
     })
 }
+
+fn parse_sqlite(param: &str) -> Result<spin::sqlite::Value> {
+    use spin::sqlite::Value as V;
+
+    Ok(if param == "null" {
+        V::Null
+    } else {
+        let (type_, value) = case_helper::split_param(param)?;
+
+        match type_ {
+            "integer" => V::Integer(value.parse()?),
+            "real" => V::Real(value.parse()?),
+            "text" => V::Text(value.into()),
+            "blob" => V::Blob(value.as_bytes().to_vec()),
+            _ => bail!("unknown parameter type: {type_}"),
+        }
+    })
+}
+
+fn sqlite_error(error: spin::sqlite::Error) -> String {
+    use spin::sqlite::Error;
+
+    match error {
+        Error::NoSuchDatabase => "no-such-database".into(),
+        Error::AccessDenied => "access-denied".into(),
+        Error::InvalidConnection => "invalid-connection".into(),
+        Error::DatabaseFull => "database-full".into(),
+        Error::Io(message) => format!("io({message})"),
+    }
+}